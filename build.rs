@@ -2,8 +2,6 @@
 
 // use gl_generator::{Registry, Api, Profile, Fallbacks, StructGenerator};
 use std::env;
-use std::fs::File;
-use std::path::Path;
 use std::path::PathBuf;
 
 fn main() {
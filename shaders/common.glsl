@@ -0,0 +1,17 @@
+// Vertex layout and stage interface shared by the sprite shaders.
+
+#ifdef VERTEX_SHADER
+layout (location = 0) in vec3 Position;
+layout (location = 1) in vec2 TexCoord;
+layout (location = 2) in vec4 Color;
+
+out VS_OUTPUT {
+    vec2 TexCoord;
+    vec4 Color;
+} OUT;
+#else
+in VS_OUTPUT {
+    vec2 TexCoord;
+    vec4 Color;
+} IN;
+#endif
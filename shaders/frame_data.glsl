@@ -0,0 +1,8 @@
+// Per-frame data, filled once per frame by the render system (binding point 0).
+
+layout (std140) uniform FrameData {
+    mat4 Projection;
+    mat4 View;
+    vec2 ViewportSize;
+    float Time;
+};
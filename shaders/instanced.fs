@@ -0,0 +1,11 @@
+#version 330 core
+
+#include "common.glsl"
+
+uniform sampler2D Texture;
+
+out vec4 Color;
+
+void main() {
+    Color = texture(Texture, IN.TexCoord) * IN.Color;
+}
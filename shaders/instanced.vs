@@ -0,0 +1,23 @@
+#version 330 core
+
+#include "common.glsl"
+#include "frame_data.glsl"
+
+// Per-instance data, advanced once per instance (see `InstanceBuffer`).
+layout (location = 3) in vec4 InstanceTransform; // translation in xyz, rotation in radians in w
+layout (location = 4) in vec3 InstanceScale;
+layout (location = 5) in vec4 InstanceRect;      // UV offset in xy, UV scale in zw
+layout (location = 6) in vec4 InstanceTint;
+
+void main() {
+    // same order as `model_matrix`: scale, rotate, translate
+    vec2 scaled = Position.xy * InstanceScale.xy;
+    float s = sin(InstanceTransform.w);
+    float c = cos(InstanceTransform.w);
+    vec3 world = vec3(c * scaled.x - s * scaled.y, s * scaled.x + c * scaled.y, Position.z * InstanceScale.z)
+        + InstanceTransform.xyz;
+    gl_Position = Projection * View * vec4(world, 1.0);
+
+    OUT.TexCoord = TexCoord * InstanceRect.zw + InstanceRect.xy;
+    OUT.Color = Color * InstanceTint;
+}
@@ -0,0 +1,51 @@
+#version 330 core
+
+// Built-in post-processing effects, one per define. `Texture` holds the previous pass.
+
+#include "frame_data.glsl"
+
+uniform sampler2D Texture;
+uniform float Strength;
+
+in vec2 UV;
+
+out vec4 Color;
+
+void main() {
+    vec2 coord = UV;
+
+#ifdef PIXELATE
+    // Strength is the size of one block in pixels
+    vec2 block = max(Strength, 1.0) / ViewportSize;
+    coord = (floor(coord / block) + 0.5) * block;
+#endif
+
+#ifdef CRT
+    // barrel distortion towards the corners
+    vec2 centered = coord * 2.0 - 1.0;
+    centered *= 1.0 + 0.1 * Strength * dot(centered.yx, centered.yx);
+    coord = centered * 0.5 + 0.5;
+#endif
+
+    vec3 color = texture(Texture, coord).rgb;
+
+#ifdef GRAYSCALE
+    float luminance = dot(color, vec3(0.2126, 0.7152, 0.0722));
+    color = mix(color, vec3(luminance), Strength);
+#endif
+
+#ifdef CRT
+    float scanline = sin(coord.y * ViewportSize.y * 3.14159) * 0.5 + 0.5;
+    color *= 1.0 - Strength * 0.5 * scanline;
+    if (coord.x < 0.0 || coord.x > 1.0 || coord.y < 0.0 || coord.y > 1.0) {
+        color = vec3(0.0);
+    }
+#endif
+
+#ifdef VIGNETTE
+    float distance = length(UV - 0.5) * 1.414;
+    color *= 1.0 - Strength * smoothstep(0.4, 1.0, distance);
+#endif
+
+    Color = vec4(color, 1.0);
+}
@@ -0,0 +1,13 @@
+#version 330 core
+
+// Full-screen quad for post-processing passes; positions are already in clip space.
+
+layout (location = 0) in vec3 Position;
+layout (location = 1) in vec2 TexCoord;
+
+out vec2 UV;
+
+void main() {
+    gl_Position = vec4(Position, 1.0);
+    UV = TexCoord;
+}
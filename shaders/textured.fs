@@ -1,15 +1,19 @@
 #version 330 core
 
-uniform sampler2D Texture;
+#include "common.glsl"
 
-in VS_OUTPUT {
-    vec2 TexCoord;
-    vec4 Color;
-} IN;
+uniform sampler2D Texture;
+#ifdef UV_OFFSET
+uniform vec2 Offset;
+#endif
 
 out vec4 Color;
 
 void main() {
-    vec4 textureColor = texture(Texture, IN.TexCoord);
+    vec2 coord = IN.TexCoord;
+#ifdef UV_OFFSET
+    coord += Offset;
+#endif
+    vec4 textureColor = texture(Texture, coord);
     Color = textureColor * IN.Color;
-}
\ No newline at end of file
+}
@@ -4,10 +4,18 @@ pub mod transform;
 pub mod sprite;
 pub mod spritesheet;
 pub mod animated_sprite;
+pub mod offscreen_camera;
+pub mod instance;
+pub mod render_layer;
+pub mod bounds;
 
 pub use self::mesh::Mesh;
 pub use self::material::Material;
 pub use self::transform::Transform;
 pub use self::sprite::Sprite;
 pub use self::spritesheet::Spritesheet;
-pub use self::animated_sprite::AnimatedSprite;
\ No newline at end of file
+pub use self::animated_sprite::AnimatedSprite;
+pub use self::offscreen_camera::OffscreenCamera;
+pub use self::instance::Instance;
+pub use self::render_layer::RenderLayer;
+pub use self::bounds::Bounds;
\ No newline at end of file
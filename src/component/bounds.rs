@@ -0,0 +1,16 @@
+use specs::{Component, VecStorage};
+use crate::rendering::Aabb;
+
+/// Cached extent of an entity's `Mesh`, kept up to date by `UpdateBounds` and tested against
+/// the camera's visible area by `Render`.
+#[derive(Debug, Clone, Copy)]
+pub struct Bounds {
+    /// Mesh-space bounds, recomputed only when the mesh vertices change.
+    pub local: Aabb,
+    /// `local` moved by the entity's `Transform`.
+    pub world: Aabb,
+}
+
+impl Component for Bounds {
+    type Storage = VecStorage<Self>;
+}
@@ -0,0 +1,26 @@
+use specs::{Component, VecStorage};
+use glm::{vec4, Vec4};
+
+/// Draws the entity as one instance of the `InstanceGroups` entry named `group`, placed by its
+/// `Transform`. Entities with this component don't need a `Mesh` or `Material` of their own.
+#[derive(Debug)]
+pub struct Instance {
+    pub group: String,
+    /// UV offset in `xy` and UV scale in `zw`, applied to the group mesh's UVs.
+    pub rect: Vec4,
+    pub tint: Vec4,
+}
+
+impl Default for Instance {
+    fn default() -> Self {
+        Instance {
+            group: String::new(),
+            rect: vec4(0., 0., 1., 1.),
+            tint: vec4(1., 1., 1., 1.),
+        }
+    }
+}
+
+impl Component for Instance {
+    type Storage = VecStorage<Self>;
+}
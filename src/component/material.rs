@@ -1,15 +1,19 @@
+use std::sync::Arc;
 use specs::{Component, VecStorage};
 use crate::rendering::texture::Texture;
+use crate::rendering::{Program, TextureDescriptor, BlendMode};
 use glm::Vec2;
-use gl;
 
 #[derive(Default, Debug)]
 pub struct Material {
     pub shader: String,  
+    pub defines: Vec<String>,
     pub texture_name: String,
-    pub program: gl::types::GLuint,
-    pub texture: Texture,
+    pub texture_descriptor: TextureDescriptor,
+    pub program: Arc<Program>,
+    pub texture: Arc<Texture>,
     pub uv_offset: Vec2,
+    pub blend: BlendMode,
 }
 
 impl Component for Material {
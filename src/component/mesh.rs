@@ -1,19 +1,151 @@
+use std::ops::Range;
 use specs::{Component, VecStorage};
-use gl;
+use glm::Vec4;
+use crate::rendering::{Aabb, Buffer, BufferUsage, VertexArray, VertexLayout, Primitive};
 
+/// Geometry drawn with a `Material`. Changes go through `set_attribute`, `push_vertex` and
+/// `set_indices`, or `vertices` is edited directly followed by `mark_vertices_dirty`.
+///
+/// Entity meshes are read from `vertices` every frame and streamed through the sprite batch,
+/// so `usage`, the dirty ranges and the GPU handles only apply to `InstanceGroup` meshes,
+/// which `UpdateMeshes` keeps up to date. On an entity mesh they have no effect.
 #[derive(Default, Debug)]
 pub struct Mesh {
-    pub vertices: Vec<f32>,
-    pub uv: Vec<f32>,
-    pub colors: Vec<f32>,
+    pub layout: VertexLayout,
+    /// Interleaved vertices as described by `layout`.
+    pub vertices: Vec<u8>,
+    /// Drawn in vertex order when empty.
     pub indices: Vec<u32>,
-    pub vao: gl::types::GLuint,
-    pub vertex_vbo: gl::types::GLuint,
-    pub uv_vbo: gl::types::GLuint,
-    pub colors_vbo: gl::types::GLuint,
-    pub ibo: gl::types::GLuint
+    pub primitive: Primitive,
+    /// Buffer hint for an instance group mesh.
+    pub usage: BufferUsage,
+    /// Byte range of `vertices` changed since the last upload.
+    pub dirty_vertices: Option<Range<usize>>,
+    pub dirty_indices: bool,
+    /// Created by `InitRender` for instance group meshes only, 0 otherwise.
+    pub vao: VertexArray,
+    pub vbo: Buffer,
+    pub ibo: Buffer
+}
+
+impl Mesh {
+    pub fn new(layout: VertexLayout) -> Self {
+        Mesh { layout, ..Default::default() }
+    }
+
+    pub fn with_primitive(mut self, primitive: Primitive) -> Self {
+        self.primitive = primitive;
+        self
+    }
+
+    pub fn with_usage(mut self, usage: BufferUsage) -> Self {
+        self.usage = usage;
+        self
+    }
+
+    /// Appends a vertex, one slice per layout attribute, and returns its index.
+    pub fn push_vertex(&mut self, values: &[&[f32]]) -> u32 {
+        let index = self.vertex_count() as u32;
+        let start = self.vertices.len();
+        self.layout.write(&mut self.vertices, values);
+        self.mark_vertices_dirty(start..self.vertices.len());
+        index
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.layout.vertex_count(&self.vertices)
+    }
+
+    /// Attribute `name` of vertex `vertex`, `None` if the layout has no such attribute.
+    pub fn attribute(&self, vertex: usize, name: &str) -> Option<Vec4> {
+        self.layout.read(&self.vertices, vertex, name)
+    }
+
+    /// Overwrites attribute `name` of vertex `vertex`. Returns false if there is no such
+    /// attribute or vertex.
+    pub fn set_attribute(&mut self, vertex: usize, name: &str, values: &[f32]) -> bool {
+        if !self.layout.set(&mut self.vertices, vertex, name, values) {
+            return false;
+        }
+        let start = vertex * self.layout.stride();
+        self.mark_vertices_dirty(start..start + self.layout.stride());
+        true
+    }
+
+    /// xy extent of the `Position` attribute, `None` for a mesh without vertices.
+    pub fn local_bounds(&self) -> Option<Aabb> {
+        Aabb::from_points((0..self.vertex_count())
+            .filter_map(|vertex| self.attribute(vertex, "Position"))
+            .map(|position| position.xy()))
+    }
+
+    pub fn set_indices(&mut self, indices: Vec<u32>) {
+        self.indices = indices;
+        self.dirty_indices = true;
+    }
+
+    /// Flags a byte range of `vertices` for upload, merged with anything already flagged.
+    pub fn mark_vertices_dirty(&mut self, range: Range<usize>) {
+        self.dirty_vertices = Some(match self.dirty_vertices.take() {
+            Some(dirty) => dirty.start.min(range.start)..dirty.end.max(range.end),
+            None => range,
+        });
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.dirty_vertices.is_some() || self.dirty_indices
+    }
+
+    pub fn is_indexed(&self) -> bool {
+        !self.indices.is_empty()
+    }
+
+    /// Number of indices, or of vertices for a mesh without indices.
+    pub fn element_count(&self) -> usize {
+        if self.is_indexed() { self.indices.len() } else { self.vertex_count() }
+    }
+
+    /// The indices, or `0..vertex_count()` for a mesh without indices.
+    pub fn elements(&self) -> Vec<u32> {
+        if self.is_indexed() { self.indices.clone() } else { (0..self.vertex_count() as u32).collect() }
+    }
 }
 
 impl Component for Mesh {
     type Storage = VecStorage<Self>;
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mesh() -> Mesh {
+        let mut mesh = Mesh::new(VertexLayout::sprite());
+        for _ in 0..4 {
+            mesh.push_vertex(&[&[0., 0., 0.]]);
+        }
+        mesh
+    }
+
+    #[test]
+    fn pushed_vertices_are_dirty() {
+        let mesh = mesh();
+        assert_eq!(mesh.dirty_vertices, Some(0..4 * mesh.layout.stride()));
+    }
+
+    #[test]
+    fn dirty_ranges_merge_into_one_covering_range() {
+        let mut mesh = mesh();
+        let stride = mesh.layout.stride();
+        mesh.dirty_vertices = None;
+
+        assert!(mesh.set_attribute(2, "TexCoord", &[1., 1.]));
+        assert_eq!(mesh.dirty_vertices, Some(2 * stride..3 * stride));
+        mesh.mark_vertices_dirty(4..8);
+        assert_eq!(mesh.dirty_vertices, Some(4..3 * stride));
+
+        assert!(!mesh.set_attribute(4, "Position", &[0., 0., 0.]));
+        assert!(!mesh.set_attribute(0, "Normal", &[0., 0., 1.]));
+        assert_eq!(mesh.dirty_vertices, Some(4..3 * stride));
+    }
+}
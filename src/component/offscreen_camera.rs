@@ -0,0 +1,18 @@
+use std::sync::Arc;
+use specs::{Component, VecStorage};
+use glm::{TMat4, TVec3, Vec4};
+use crate::rendering::RenderTarget;
+
+/// Renders the scene from `position` into `target` before the main view is drawn.
+/// Put `target.color` into a `Material` to show the result.
+#[derive(Default)]
+pub struct OffscreenCamera {
+    pub position: TVec3<f32>,
+    pub projection: TMat4<f32>,
+    pub clear_color: Vec4,
+    pub target: Arc<RenderTarget>,
+}
+
+impl Component for OffscreenCamera {
+    type Storage = VecStorage<Self>;
+}
@@ -0,0 +1,25 @@
+use specs::{Component, VecStorage};
+
+/// Where an entity's sprite falls in the back-to-front draw order: lower layers first, then
+/// lower `Transform::position.z`. With `y_sort`, sprites higher up the screen are drawn before
+/// the ones below them, for top-down scenes. Entities without one are on layer 0.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RenderLayer {
+    pub layer: i32,
+    pub y_sort: bool,
+}
+
+impl RenderLayer {
+    pub fn new(layer: i32) -> Self {
+        RenderLayer { layer, y_sort: false }
+    }
+
+    pub fn with_y_sort(mut self, y_sort: bool) -> Self {
+        self.y_sort = y_sort;
+        self
+    }
+}
+
+impl Component for RenderLayer {
+    type Storage = VecStorage<Self>;
+}
@@ -1,9 +1,14 @@
+use std::sync::Arc;
 use specs::{Component, VecStorage};
 use glm::Vec4;
+use crate::rendering::{Texture, TextureDescriptor};
 
 #[derive(Default, Debug)]
 pub struct Sprite {
     pub image_name: String,
+    /// Filled from the `TextureCache` by the sprite init systems.
+    pub texture: Arc<Texture>,
+    pub texture_descriptor: TextureDescriptor,
     pub rect: Vec4
 }
 
@@ -0,0 +1,9 @@
+extern crate stb_image;
+extern crate nalgebra_glm as glm;
+extern crate gl;
+
+pub mod component;
+pub mod system;
+pub mod rendering;
+pub mod resource;
+pub mod common;
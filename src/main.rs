@@ -1,28 +1,20 @@
 extern crate sdl2;
-extern crate stb_image;
-extern crate nalgebra as na;
 extern crate nalgebra_glm as glm;
-extern crate num_traits;
 extern crate gl;
 extern crate imgui;
 extern crate imgui_sdl2;
 extern crate imgui_opengl_renderer;
 
-mod component;
-mod system;
-mod rendering;
-mod resource;
-mod common;
-
-use sdl2::event::Event;
+use sdl2::event::{Event, WindowEvent};
 use sdl2::keyboard::Keycode;
 use std::time::{Duration, Instant};
 use specs::{Builder, World, WorldExt, RunNow, DispatcherBuilder};
-use glm::{vec1, vec3, vec4};
-use crate::component::{Transform, Mesh, Material, Sprite, Spritesheet, AnimatedSprite};
-use crate::resource::{Projection, Camera, Keyboard, KeycodeEx, DeltaTime};
-use crate::system::{InitRender, InitSprite, InitAnimatedSprite, UpdateAnimatedSprite, Render, KeyboardInput};
-use crate::common::deg2rad;
+use glm::{vec3, vec4};
+use learn_gl::component::{Transform, Mesh, Material, Sprite, Spritesheet, AnimatedSprite, OffscreenCamera, Instance, RenderLayer, Bounds};
+use learn_gl::resource::{Camera, Keyboard, KeycodeEx, DeltaTime, ElapsedTime, Viewport, ClearColor, RenderDevice, ShaderWatcher, ShaderErrors, ShaderLibrary, TextureCache, PostProcessStack, InstanceGroups, RenderStats, ScreenScaling, ScalingMode};
+use learn_gl::rendering::{GlBackend, Preprocessor, resolve_path, read_pixels, screenshot_path};
+use learn_gl::system::{InitRender, InitSprite, InitAnimatedSprite, UpdateAnimatedSprite, Render, KeyboardInput, ReloadShaders, UpdateMeshes, UpdateBounds};
+use learn_gl::common::deg2rad;
 
 fn main() -> Result<(), String> {
     let sdl_context = sdl2::init()?;
@@ -37,11 +29,12 @@ fn main() -> Result<(), String> {
         .position_centered()
         .opengl()
         .resizable()
+        .allow_highdpi()
         .build()
         .map_err(|e| e.to_string())?;
 
     let _gl_context = window.gl_create_context()?;
-    let _gl = gl::load_with(|s| video_subsystem.gl_get_proc_address(s) as *const std::os::raw::c_void);
+    gl::load_with(|s| video_subsystem.gl_get_proc_address(s) as *const std::os::raw::c_void);
 
     let mut imgui = imgui::Context::create();
     imgui.set_ini_filename(None);
@@ -56,6 +49,10 @@ fn main() -> Result<(), String> {
     world.register::<Sprite>();
     world.register::<Spritesheet>();
     world.register::<AnimatedSprite>();
+    world.register::<OffscreenCamera>();
+    world.register::<Instance>();
+    world.register::<RenderLayer>();
+    world.register::<Bounds>();
 
     // world.create_entity()
     //     .with(Transform { position: vec3(100., 100., 0.) })
@@ -96,7 +93,8 @@ fn main() -> Result<(), String> {
          })
         .with(Sprite { 
             image_name: "tower.png".to_string(), 
-            rect: vec4(0., 0., 205., 198.)
+            rect: vec4(0., 0., 205., 198.),
+            ..Default::default()
          })
         .build();
 
@@ -113,7 +111,8 @@ fn main() -> Result<(), String> {
          })
         .with(Spritesheet {
             image_name: "tileset.png".to_string(),
-            rects: rect_anims.clone() 
+            rects: rect_anims.clone(),
+            ..Default::default()
         })
         .with(AnimatedSprite {
             rects: vec![
@@ -125,17 +124,35 @@ fn main() -> Result<(), String> {
             frame_time: 0.5,
             tick: 0.
         })
+        .with(RenderLayer::new(1))
         .build();
 
-    world.insert(Projection(glm::ortho(0., 900., 0., 700., -1., 1.)));
+    world.insert(ScreenScaling::new(900., 700.));
+    apply_screen_scaling(&mut world, &window);
     world.insert(Camera(glm::vec3(0., 0., 0.)));
     world.insert(Keyboard::default());
     world.insert(DeltaTime(0.0));
+    world.insert(ElapsedTime(0.0));
+    world.insert(ClearColor::default());
+    world.insert(RenderDevice::new(GlBackend::new()));
+    let preprocessor = Preprocessor::default().with_gl_version(4, 4, true);
+    let shader_dir = resolve_path(&preprocessor.shader_dir).map_err(|e| e.to_string())?;
+    world.insert(ShaderWatcher::new(shader_dir, Duration::from_millis(500)));
+    world.insert(ShaderErrors::default());
+    world.insert(ShaderLibrary::default());
+    world.insert(TextureCache::default());
+    world.insert(PostProcessStack::builtin());
+    world.insert(InstanceGroups::default());
+    world.insert(RenderStats::default());
+    world.insert(preprocessor);
 
     let mut dispatcher = DispatcherBuilder::new()
         .with(KeyboardInput, "keyboard_input", &[])
         .with(UpdateAnimatedSprite, "update_animated_sprite", &["keyboard_input"])
-        .with_thread_local(Render)
+        .with_thread_local(ReloadShaders)
+        .with_thread_local(UpdateBounds)
+        .with_thread_local(UpdateMeshes)
+        .with_thread_local(Render::default())
         .build();
 
     let mut init_sprite = InitSprite;
@@ -156,8 +173,11 @@ fn main() -> Result<(), String> {
         current_time = temp_current_time;
         *world.write_resource::<DeltaTime>() = DeltaTime(
             delta.as_secs() as f32 + delta.subsec_nanos() as f32 * 1e-9);
+        *world.write_resource::<ElapsedTime>() = ElapsedTime(current_time.as_secs_f32());
 
         let mut keyboard = Keyboard::default();
+        let mut take_screenshot = false;
+        let mut resized = false;
 
         for event in event_pump.poll_iter() {
             imgui_sdl2.handle_event(&mut imgui, &event);
@@ -183,10 +203,23 @@ fn main() -> Result<(), String> {
                     println!("Left Pressed");
                     keyboard = Keyboard(KeycodeEx::LeftArrow);
                 },
+                Event::KeyDown {
+                    keycode: Some(Keycode::F12),
+                    repeat: false,
+                    ..
+                } => take_screenshot = true,
+                Event::Window {
+                    win_event: WindowEvent::SizeChanged(..),
+                    ..
+                } => resized = true,
                 _ => {}
             }
         }
 
+        if resized {
+            apply_screen_scaling(&mut world, &window);
+        }
+
         *world.write_resource::<Keyboard>() = keyboard;
 
         imgui_sdl2.prepare_frame(imgui.io_mut(), &window, &event_pump.mouse_state());
@@ -196,9 +229,85 @@ fn main() -> Result<(), String> {
         let ui = imgui.frame();
         ui.show_demo_window(&mut true);
 
-        dispatcher.dispatch(&mut world);
+        dispatcher.dispatch(&world);
         world.maintain();
 
+        // taken before the imgui overlay is drawn on top
+        if take_screenshot {
+            let viewport = *world.read_resource::<Viewport>();
+            let mut device = world.write_resource::<RenderDevice>();
+            let image = read_pixels(device.0.as_mut(), 0, viewport.x, viewport.y,
+                viewport.width as usize, viewport.height as usize, true);
+            let path = screenshot_path("screenshots");
+            match image.save_png(&path) {
+                Ok(()) => println!("Saved screenshot {}", path.display()),
+                Err(err) => eprintln!("{}", err),
+            }
+        }
+
+        {
+            let shader_errors = world.read_resource::<ShaderErrors>();
+            if !shader_errors.errors.is_empty() || !shader_errors.reloaded.is_empty() {
+                imgui::Window::new(imgui::im_str!("Shaders"))
+                    .position([10., 10.], imgui::Condition::FirstUseEver)
+                    .always_auto_resize(true)
+                    .build(&ui, || {
+                        for label in shader_errors.reloaded.iter() {
+                            ui.text_colored([0.4, 1.0, 0.4, 1.0], format!("Reloaded {}", label));
+                        }
+                        for (label, error) in shader_errors.errors.iter() {
+                            ui.text(label);
+                            ui.text_colored([1.0, 0.4, 0.4, 1.0], error);
+                        }
+                    });
+            }
+        }
+
+        {
+            let mut post_process = world.write_resource::<PostProcessStack>();
+            imgui::Window::new(imgui::im_str!("Post-processing"))
+                .position([10., 400.], imgui::Condition::FirstUseEver)
+                .always_auto_resize(true)
+                .build(&ui, || {
+                    for (index, pass) in post_process.passes.iter_mut().enumerate() {
+                        let id = ui.push_id(index as i32);
+                        ui.checkbox(&imgui::ImString::new(pass.name.as_str()), &mut pass.enabled);
+                        let (min, max) = pass.strength_range;
+                        imgui::Slider::new(imgui::im_str!("strength"))
+                            .range(min..=max)
+                            .build(&ui, &mut pass.strength);
+                        id.pop(&ui);
+                    }
+                });
+        }
+
+        {
+            let stats = world.read_resource::<RenderStats>();
+            imgui::Window::new(imgui::im_str!("Render stats"))
+                .position([600., 10.], imgui::Condition::FirstUseEver)
+                .always_auto_resize(true)
+                .build(&ui, || {
+                    ui.text(format!("visible: {}", stats.visible));
+                    ui.text(format!("culled: {}", stats.culled));
+                    ui.text(format!("draw calls: {}", stats.draw_calls));
+                });
+        }
+
+        let mut scaling = *world.read_resource::<ScreenScaling>();
+        imgui::Window::new(imgui::im_str!("Screen scaling"))
+            .position([600., 120.], imgui::Condition::FirstUseEver)
+            .always_auto_resize(true)
+            .build(&ui, || {
+                ui.radio_button(imgui::im_str!("stretch"), &mut scaling.mode, ScalingMode::Stretch);
+                ui.radio_button(imgui::im_str!("letterbox"), &mut scaling.mode, ScalingMode::Letterbox);
+                ui.radio_button(imgui::im_str!("expand"), &mut scaling.mode, ScalingMode::Expand);
+                ui.radio_button(imgui::im_str!("integer"), &mut scaling.mode, ScalingMode::Integer);
+            });
+        if scaling.mode != world.read_resource::<ScreenScaling>().mode {
+            world.insert(scaling);
+            apply_screen_scaling(&mut world, &window);
+        }
+
         imgui_sdl2.prepare_render(&ui, &window);
         imgui_renderer.render(ui);
 
@@ -207,4 +316,13 @@ fn main() -> Result<(), String> {
     }
 
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Fits `ScreenScaling` into the window's drawable size, which is in pixels rather than points
+/// so HiDPI displays render at full resolution.
+fn apply_screen_scaling(world: &mut World, window: &sdl2::video::Window) {
+    let (width, height) = window.drawable_size();
+    let (viewport, projection) = world.read_resource::<ScreenScaling>().apply(width, height);
+    world.insert(viewport);
+    world.insert(projection);
+}
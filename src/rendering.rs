@@ -2,37 +2,57 @@ pub mod shader;
 pub mod resource;
 pub mod buffer;
 pub mod texture;
+pub mod texture_descriptor;
+pub mod texture_format;
 pub mod transform;
+pub mod backend;
+pub mod error;
+pub mod preprocess;
+pub mod uniform;
+pub mod uniform_block;
+pub mod atlas;
+pub mod framebuffer;
+pub mod screenshot;
+pub mod blend_mode;
+pub mod sprite_batch;
+pub mod instancing;
+pub mod vertex_layout;
+pub mod primitive;
+pub mod aabb;
 
 pub use self::shader::{
-    create_program, 
-    shader_from_source,
-    use_program,
-    get_uniform_location,
-    get_attrib_location,
-    push_uniform_vec2,
-    push_uniform_vec3
+    Shader,
+    Program,
+    ActiveVariable,
+    load_program
 };
-pub use self::resource::load_cstring;
+pub use self::resource::{load_string, resolve_path};
 pub use self::buffer::{
-    new_buffer,
-    bind_buffer,
-    unbind_buffer,
-    new_vertex_array,
-    bind_vertex_array,
-    unbind_vertex_array,
-    vertex_attrib_pointer
+    Buffer,
+    BufferUsage,
+    VertexArray,
+    as_bytes
 };
 pub use self::texture::{
-    load_texture, 
-    set_texture_to_program,
-    gen_texture,
-    bind_texture_unit,
-    bind_texture,
-    unbind_texture,
-    get_current_bound_texture,
-    set_texture_filter,
-    set_pixel_store_mode,
-    set_texture_2d
+    Texture,
+    load_texture,
+    create_texture,
+    set_texture_to_program
 };
-pub use self::transform::set_mvp_to_program;
\ No newline at end of file
+pub use self::texture_descriptor::TextureDescriptor;
+pub use self::texture_format::TextureFormat;
+pub use self::atlas::{AtlasBuilder, TextureAtlas, AtlasRegion};
+pub use self::framebuffer::RenderTarget;
+pub use self::screenshot::{Image, read_pixels, read_render_target, screenshot_path};
+pub use self::blend_mode::BlendMode;
+pub use self::sprite_batch::{SpriteBatch, BatchKey, BatchVertex, DrawOrder};
+pub use self::instancing::{InstanceBuffer, InstanceData, instance_layout};
+pub use self::vertex_layout::{VertexLayout, VertexAttribute, AttributeReader};
+pub use self::primitive::Primitive;
+pub use self::aabb::Aabb;
+pub use self::transform::model_matrix;
+pub use self::error::{RenderError, ShaderDiagnostic};
+pub use self::preprocess::{Preprocessor, ShaderSource, SourceLine};
+pub use self::uniform::{Uniform, UniformValue, TextureUnit};
+pub use self::uniform_block::{Std140Writer, FrameData, UniformBlock, FRAME_DATA_BLOCK, FRAME_DATA_BINDING};
+pub use self::backend::{RenderBackend, GlBackend, RecordingBackend, RenderCommand, CommandLog, SoftwareBackend};
\ No newline at end of file
@@ -0,0 +1,46 @@
+use glm::{vec2, vec4, Mat4, Vec2};
+
+/// Axis-aligned rectangle in the xy plane, used for culling.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: Vec2,
+    pub max: Vec2,
+}
+
+impl Aabb {
+    pub fn new(min: Vec2, max: Vec2) -> Self {
+        Aabb { min, max }
+    }
+
+    /// The smallest rectangle holding every point, `None` without points.
+    pub fn from_points<I: IntoIterator<Item = Vec2>>(points: I) -> Option<Aabb> {
+        points.into_iter().fold(None, |bounds, point| Some(match bounds {
+            Some(Aabb { min, max }) => Aabb::new(min.inf(&point), max.sup(&point)),
+            None => Aabb::new(point, point),
+        }))
+    }
+
+    /// The xy area `view_projection` maps into clip space, i.e. what a camera can see.
+    pub fn visible_area(view_projection: &Mat4) -> Aabb {
+        let clip_to_world = view_projection.try_inverse().unwrap_or_else(Mat4::identity);
+        Aabb::new(vec2(-1., -1.), vec2(1., 1.)).transformed(&clip_to_world)
+    }
+
+    pub fn corners(&self) -> [Vec2; 4] {
+        [self.min, vec2(self.max.x, self.min.y), self.max, vec2(self.min.x, self.max.y)]
+    }
+
+    /// Bounds of this rectangle after `matrix`, which may rotate it.
+    pub fn transformed(&self, matrix: &Mat4) -> Aabb {
+        let corners = self.corners().iter()
+            .map(|corner| (matrix * vec4(corner.x, corner.y, 0., 1.)).xy())
+            .collect::<Vec<_>>();
+        Aabb::from_points(corners).unwrap_or(*self)
+    }
+
+    /// True if the rectangles overlap or touch.
+    pub fn intersects(&self, other: &Aabb) -> bool {
+        self.min.x <= other.max.x && other.min.x <= self.max.x
+            && self.min.y <= other.max.y && other.min.y <= self.max.y
+    }
+}
@@ -0,0 +1,281 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use glm::{vec4, Vec4};
+use stb_image::image::{load_with_depth, LoadResult};
+use crate::rendering::{RenderBackend, RenderError, Texture, TextureDescriptor, TextureFormat, create_texture};
+
+/// Where one packed image ended up: the page it lives on and its pixel rect
+/// `(x, y, width, height)`, laid out like `Sprite::rect`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AtlasRegion {
+    pub page: usize,
+    pub rect: Vec4,
+}
+
+/// Packed pages plus the named regions on them.
+#[derive(Debug, Default)]
+pub struct TextureAtlas {
+    pub name: String,
+    pub pages: Vec<Arc<Texture>>,
+    pub regions: HashMap<String, AtlasRegion>,
+}
+
+impl TextureAtlas {
+    pub fn region(&self, name: &str) -> Option<&AtlasRegion> {
+        self.regions.get(name)
+    }
+
+    /// Name under which `page` is registered in the `TextureCache`, for `Sprite::image_name`.
+    pub fn page_name(&self, page: usize) -> String {
+        format!("{}#{}", self.name, page)
+    }
+
+    /// Rects of several regions, e.g. the frames of a `Spritesheet`. `None` if any is missing
+    /// or they don't all share a page.
+    pub fn rects(&self, names: &[&str]) -> Option<(usize, Vec<Vec4>)> {
+        let regions = names.iter().map(|name| self.region(name)).collect::<Option<Vec<_>>>()?;
+        let page = regions.first()?.page;
+        if regions.iter().any(|region| region.page != page) {
+            return None;
+        }
+
+        Some((page, regions.into_iter().map(|region| region.rect).collect()))
+    }
+}
+
+struct AtlasImage {
+    name: String,
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+}
+
+/// Packs RGBA images into as few `max_size` pages as possible. Every image is surrounded by
+/// `extrude` copies of its border pixels, so filtering and mipmaps don't pull in neighbours,
+/// and `padding` transparent pixels.
+pub struct AtlasBuilder {
+    name: String,
+    max_size: usize,
+    padding: usize,
+    extrude: usize,
+    descriptor: TextureDescriptor,
+    images: Vec<AtlasImage>,
+}
+
+impl AtlasBuilder {
+    pub fn new(name: &str, max_size: usize) -> Self {
+        AtlasBuilder {
+            name: name.to_string(),
+            max_size,
+            padding: 1,
+            extrude: 1,
+            descriptor: TextureDescriptor::pixel_art(),
+            images: vec![],
+        }
+    }
+
+    pub fn with_padding(mut self, padding: usize) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    pub fn with_extrude(mut self, extrude: usize) -> Self {
+        self.extrude = extrude;
+        self
+    }
+
+    pub fn with_descriptor(mut self, descriptor: TextureDescriptor) -> Self {
+        self.descriptor = descriptor;
+        self
+    }
+
+    /// Adds tightly packed RGBA8 pixels.
+    pub fn add_image(&mut self, name: &str, width: usize, height: usize, pixels: Vec<u8>) -> &mut Self {
+        debug_assert_eq!(pixels.len(), width * height * 4);
+        self.images.push(AtlasImage { name: name.to_string(), width, height, pixels });
+        self
+    }
+
+    /// Decodes an image file as RGBA8 and adds it under its path.
+    pub fn add_file(&mut self, path: &str) -> Result<&mut Self, RenderError> {
+        match load_with_depth(path, 4, true) {
+            LoadResult::ImageU8(img) => Ok(self.add_image(path, img.width, img.height, img.data)),
+            LoadResult::ImageF32(_) => Err(RenderError::ImageDecode {
+                path: path.to_string(),
+                message: "float images can't be packed into an 8-bit atlas".to_string(),
+            }),
+            LoadResult::Error(message) => Err(RenderError::ImageDecode { path: path.to_string(), message }),
+        }
+    }
+
+    pub fn build(&self, backend: &mut dyn RenderBackend) -> Result<TextureAtlas, RenderError> {
+        let border = self.extrude * 2 + self.padding;
+
+        // tallest first keeps the skyline flat
+        let mut order: Vec<usize> = (0..self.images.len()).collect();
+        order.sort_by_key(|&index| std::cmp::Reverse((self.images[index].height, self.images[index].width)));
+
+        let mut pages: Vec<Skyline> = vec![];
+        let mut placements = vec![(0, 0, 0); self.images.len()];
+
+        for index in order {
+            let image = &self.images[index];
+            let (width, height) = (image.width + border, image.height + border);
+            if width > self.max_size || height > self.max_size {
+                return Err(RenderError::AtlasOverflow {
+                    name: image.name.clone(),
+                    width: image.width,
+                    height: image.height,
+                    max_size: self.max_size,
+                });
+            }
+
+            let placed = pages.iter_mut().enumerate()
+                .find_map(|(page, skyline)| skyline.insert(width, height).map(|(x, y)| (page, x, y)));
+            placements[index] = match placed {
+                Some(placement) => placement,
+                None => {
+                    let mut skyline = Skyline::new(self.max_size, self.max_size);
+                    let (x, y) = skyline.insert(width, height).unwrap();
+                    pages.push(skyline);
+                    (pages.len() - 1, x, y)
+                }
+            };
+        }
+
+        // pages are as wide as allowed but only as tall as they need to be
+        let page_sizes: Vec<(usize, usize)> = pages.iter()
+            .map(|skyline| (self.max_size, skyline.used_height().next_power_of_two().min(self.max_size)))
+            .collect();
+        let mut page_pixels: Vec<Vec<u8>> = page_sizes.iter().map(|(width, height)| vec![0; width * height * 4]).collect();
+
+        let mut regions = HashMap::new();
+        for (image, &(page, x, y)) in self.images.iter().zip(&placements) {
+            let (page_width, _) = page_sizes[page];
+            blit_extruded(&mut page_pixels[page], page_width, image, x, y, self.extrude);
+
+            let (left, top) = (x + self.extrude, y + self.extrude);
+            regions.insert(image.name.clone(), AtlasRegion {
+                page,
+                rect: vec4(left as f32, top as f32, image.width as f32, image.height as f32),
+            });
+        }
+
+        let format = TextureFormat::from_u8(4, &self.descriptor).unwrap_or_default();
+        let pages = page_sizes.iter().zip(&page_pixels)
+            .map(|(&(width, height), pixels)| {
+                create_texture(backend, width, height, format, &self.descriptor, pixels).map(Arc::new)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(TextureAtlas { name: self.name.clone(), pages, regions })
+    }
+}
+
+fn blit_extruded(page: &mut [u8], page_width: usize, image: &AtlasImage, x: usize, y: usize, extrude: usize) {
+    if image.width == 0 || image.height == 0 {
+        return;
+    }
+
+    let total_width = image.width + extrude * 2;
+    let total_height = image.height + extrude * 2;
+
+    for row in 0..total_height {
+        let src_y = row.saturating_sub(extrude).min(image.height - 1);
+        for column in 0..total_width {
+            let src_x = column.saturating_sub(extrude).min(image.width - 1);
+            let src = (src_y * image.width + src_x) * 4;
+            let dst = ((y + row) * page_width + x + column) * 4;
+            page[dst..dst + 4].copy_from_slice(&image.pixels[src..src + 4]);
+        }
+    }
+}
+
+/// Bottom-left skyline packer: keeps the top edge of the packed area as a list of
+/// horizontal segments and puts each rect where its top ends up lowest.
+struct Skyline {
+    width: usize,
+    height: usize,
+    // (x, y, width) of each segment, left to right
+    nodes: Vec<(usize, usize, usize)>,
+}
+
+impl Skyline {
+    fn new(width: usize, height: usize) -> Self {
+        Skyline { width, height, nodes: vec![(0, 0, width)] }
+    }
+
+    fn used_height(&self) -> usize {
+        self.nodes.iter().map(|&(_, y, _)| y).max().unwrap_or(0).max(1)
+    }
+
+    fn insert(&mut self, width: usize, height: usize) -> Option<(usize, usize)> {
+        let mut best: Option<(usize, usize, usize)> = None;
+        for index in 0..self.nodes.len() {
+            if let Some(y) = self.fit(index, width, height) {
+                let x = self.nodes[index].0;
+                if best.map(|(_, best_x, best_y)| (y, x) < (best_y, best_x)).unwrap_or(true) {
+                    best = Some((index, x, y));
+                }
+            }
+        }
+
+        let (index, x, y) = best?;
+        self.add_level(index, x, y + height, width);
+
+        Some((x, y))
+    }
+
+    fn fit(&self, index: usize, width: usize, height: usize) -> Option<usize> {
+        let x = self.nodes[index].0;
+        if x + width > self.width {
+            return None;
+        }
+
+        let mut y = 0;
+        let mut covered = 0;
+        for &(_, node_y, node_width) in &self.nodes[index..] {
+            y = y.max(node_y);
+            if y + height > self.height {
+                return None;
+            }
+            covered += node_width;
+            if covered >= width {
+                return Some(y);
+            }
+        }
+
+        None
+    }
+
+    fn add_level(&mut self, index: usize, x: usize, y: usize, width: usize) {
+        self.nodes.insert(index, (x, y, width));
+
+        // trim the segments now hidden under the new one
+        let end = x + width;
+        let next = index + 1;
+        while next < self.nodes.len() {
+            let (node_x, node_y, node_width) = self.nodes[next];
+            if node_x >= end {
+                break;
+            }
+            if node_x + node_width <= end {
+                self.nodes.remove(next);
+            } else {
+                self.nodes[next] = (end, node_y, node_x + node_width - end);
+                break;
+            }
+        }
+
+        // merge neighbours at the same height
+        let mut index = 0;
+        while index + 1 < self.nodes.len() {
+            if self.nodes[index].1 == self.nodes[index + 1].1 {
+                self.nodes[index].2 += self.nodes[index + 1].2;
+                self.nodes.remove(index + 1);
+            } else {
+                index += 1;
+            }
+        }
+    }
+}
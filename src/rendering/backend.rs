@@ -0,0 +1,140 @@
+pub mod opengl;
+pub mod recording;
+pub mod software;
+pub mod glsl;
+
+pub use self::opengl::GlBackend;
+pub use self::recording::{RecordingBackend, RenderCommand, CommandLog};
+pub use self::software::SoftwareBackend;
+
+use std::any::Any;
+use std::ffi::CStr;
+use std::sync::{Arc, Mutex};
+use gl;
+use crate::rendering::{RenderError, ActiveVariable, UniformValue, TextureDescriptor, VertexAttribute};
+
+/// A GL object whose owning handle has been dropped and which still has to be deleted.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GpuObject {
+    Shader(gl::types::GLuint),
+    Program(gl::types::GLuint),
+    Buffer(gl::types::GLuint),
+    VertexArray(gl::types::GLuint),
+    Texture(gl::types::GLuint),
+    Framebuffer(gl::types::GLuint),
+    Renderbuffer(gl::types::GLuint),
+}
+
+/// Objects released by handle `Drop`s. Handles may be dropped on any thread (e.g. during
+/// `World::maintain`), so deletion is deferred until the backend runs `collect_garbage`.
+#[derive(Default, Debug, Clone)]
+pub struct ReleaseQueue(Arc<Mutex<Vec<GpuObject>>>);
+
+impl ReleaseQueue {
+    pub fn release(&self, object: GpuObject) {
+        self.0.lock().unwrap().push(object);
+    }
+
+    pub fn drain(&self) -> Vec<GpuObject> {
+        self.0.lock().unwrap().drain(..).collect()
+    }
+}
+
+/// Everything the render systems need from the graphics API. `GlBackend` forwards
+/// to OpenGL, `RecordingBackend` only logs the calls so the systems can run headless and
+/// `SoftwareBackend` rasterizes them on the CPU.
+pub trait RenderBackend: Send + Sync {
+    // lifetime
+    fn release_queue(&self) -> ReleaseQueue;
+    fn delete_shader(&mut self, shader: gl::types::GLuint);
+    fn delete_program(&mut self, program: gl::types::GLuint);
+    fn delete_buffer(&mut self, index: gl::types::GLuint);
+    fn delete_vertex_array(&mut self, vao: gl::types::GLuint);
+    fn delete_texture(&mut self, texture: gl::types::GLuint);
+    fn delete_framebuffer(&mut self, framebuffer: gl::types::GLuint);
+    fn delete_renderbuffer(&mut self, renderbuffer: gl::types::GLuint);
+
+    fn collect_garbage(&mut self) {
+        for object in self.release_queue().drain() {
+            match object {
+                GpuObject::Shader(shader) => self.delete_shader(shader),
+                GpuObject::Program(program) => self.delete_program(program),
+                GpuObject::Buffer(index) => self.delete_buffer(index),
+                GpuObject::VertexArray(vao) => self.delete_vertex_array(vao),
+                GpuObject::Texture(texture) => self.delete_texture(texture),
+                GpuObject::Framebuffer(framebuffer) => self.delete_framebuffer(framebuffer),
+                GpuObject::Renderbuffer(renderbuffer) => self.delete_renderbuffer(renderbuffer),
+            }
+        }
+    }
+
+    // state
+    fn viewport(&mut self, x: i32, y: i32, width: i32, height: i32);
+    fn clear_color(&mut self, r: f32, g: f32, b: f32, a: f32);
+    fn clear(&mut self, mask: gl::types::GLbitfield);
+    fn enable_blend(&mut self, src: gl::types::GLenum, dst: gl::types::GLenum);
+
+    // buffers
+    /// `usage` is a hint such as `gl::STATIC_DRAW`.
+    fn new_buffer(&mut self, data: &[u8], target: gl::types::GLenum, usage: gl::types::GLenum) -> Result<gl::types::GLuint, RenderError>;
+    /// Replaces the storage of buffer `index` with `data`.
+    fn buffer_data(&mut self, target: gl::types::GLenum, index: gl::types::GLuint, data: &[u8], usage: gl::types::GLenum);
+    fn bind_buffer(&mut self, target: gl::types::GLenum, index: gl::types::GLuint);
+    fn unbind_buffer(&mut self, target: gl::types::GLenum);
+    fn buffer_sub_data(&mut self, target: gl::types::GLenum, index: gl::types::GLuint, offset: usize, data: &[u8]);
+    fn bind_buffer_base(&mut self, target: gl::types::GLenum, binding: gl::types::GLuint, index: gl::types::GLuint);
+    fn new_vertex_array(&mut self) -> Result<gl::types::GLuint, RenderError>;
+    fn bind_vertex_array(&mut self, vao: gl::types::GLuint);
+    fn unbind_vertex_array(&mut self);
+    /// Points `attribute` of the bound vertex array at `buffer_index`, `offset` bytes into each
+    /// `stride`-byte vertex.
+    fn vertex_attrib_pointer(&mut self, buffer_index: gl::types::GLuint, attribute: &VertexAttribute,
+        stride: gl::types::GLsizei, offset: usize);
+    /// Advances attribute `index` of the bound vertex array once per `divisor` instances
+    /// instead of once per vertex; 0 restores per-vertex data.
+    fn vertex_attrib_divisor(&mut self, index: gl::types::GLuint, divisor: gl::types::GLuint);
+
+    // textures
+    fn gen_texture(&mut self) -> Result<gl::types::GLuint, RenderError>;
+    fn bind_texture(&mut self, texture: gl::types::GLuint);
+    fn bind_texture_unit(&mut self, active_texture: gl::types::GLenum, texture: gl::types::GLuint);
+    fn unbind_texture(&mut self);
+    fn set_texture_sampling(&mut self, descriptor: &TextureDescriptor);
+    fn generate_mipmap(&mut self);
+    fn set_texture_swizzle(&mut self, swizzle: [gl::types::GLenum; 4]);
+    fn set_pixel_store_mode(&mut self, mode: gl::types::GLenum, value: gl::types::GLint);
+    fn set_texture_2d(&mut self, internal_format: gl::types::GLenum, mode: gl::types::GLenum, width: i32, height: i32, data_type: gl::types::GLenum, data: &[u8]);
+
+    // framebuffers
+    fn new_framebuffer(&mut self) -> Result<gl::types::GLuint, RenderError>;
+    /// Binds `framebuffer` for drawing and reading; 0 is the window.
+    fn bind_framebuffer(&mut self, framebuffer: gl::types::GLuint);
+    fn framebuffer_texture(&mut self, attachment: gl::types::GLenum, texture: gl::types::GLuint);
+    fn new_renderbuffer(&mut self, internal_format: gl::types::GLenum, width: i32, height: i32) -> Result<gl::types::GLuint, RenderError>;
+    fn framebuffer_renderbuffer(&mut self, attachment: gl::types::GLenum, renderbuffer: gl::types::GLuint);
+    fn check_framebuffer_status(&mut self) -> gl::types::GLenum;
+    /// Reads RGBA8 pixels of the bound framebuffer into `data`, bottom row first.
+    fn read_pixels(&mut self, x: i32, y: i32, width: i32, height: i32, data: &mut [u8]);
+
+    // programs
+    fn shader_from_source(&mut self, name: &str, source: &CStr, kind: gl::types::GLenum) -> Result<gl::types::GLuint, RenderError>;
+    fn create_program(&mut self, name: &str, shaders: [gl::types::GLuint; 2]) -> Result<gl::types::GLuint, RenderError>;
+    fn use_program(&mut self, program: gl::types::GLuint);
+    fn get_uniform_location(&mut self, program: gl::types::GLuint, name: &str) -> Result<gl::types::GLint, RenderError>;
+    fn active_uniforms(&mut self, program: gl::types::GLuint) -> Vec<ActiveVariable>;
+    fn active_attributes(&mut self, program: gl::types::GLuint) -> Vec<ActiveVariable>;
+    fn active_uniform_blocks(&mut self, program: gl::types::GLuint) -> Vec<String>;
+    fn uniform_block_binding(&mut self, program: gl::types::GLuint, name: &str, binding: gl::types::GLuint) -> Result<(), RenderError>;
+    fn set_uniform(&mut self, location: gl::types::GLint, value: &UniformValue);
+
+    // draw
+    fn draw_elements(&mut self, mode: gl::types::GLenum, count: i32, index_type: gl::types::GLenum, offset: usize);
+    fn draw_elements_instanced(&mut self, mode: gl::types::GLenum, count: i32, index_type: gl::types::GLenum, offset: usize, instances: i32);
+    /// Draws `count` vertices starting at `first` without an element buffer.
+    fn draw_arrays(&mut self, mode: gl::types::GLenum, first: i32, count: i32);
+    fn draw_arrays_instanced(&mut self, mode: gl::types::GLenum, first: i32, count: i32, instances: i32);
+
+    // inspection
+    /// The concrete backend, e.g. to read the `SoftwareBackend` window back in tests.
+    fn as_any(&self) -> &dyn Any;
+}
@@ -0,0 +1,150 @@
+use std::collections::HashSet;
+use gl;
+use crate::rendering::ActiveVariable;
+
+#[derive(Default, Debug, Clone)]
+pub struct Reflection {
+    pub uniforms: Vec<ActiveVariable>,
+    pub attributes: Vec<ActiveVariable>,
+    pub uniform_blocks: Vec<String>,
+}
+
+/// Best-effort reflection of GLSL source for backends without a real compiler. Honours
+/// `#define`/`#ifdef`/`#ifndef`/`#else`/`#endif`, then collects plain `uniform` declarations
+/// and uniform block names of every stage and the `in` declarations of the vertex stage.
+pub fn reflect(stages: &[(gl::types::GLenum, &str)]) -> Reflection {
+    let mut uniforms: Vec<ActiveVariable> = vec![];
+    let mut attributes: Vec<ActiveVariable> = vec![];
+    let mut uniform_blocks: Vec<String> = vec![];
+
+    for (kind, source) in stages {
+        for line in active_lines(source) {
+            let (location, declaration) = split_layout(&line);
+            let mut tokens = declaration.split(|c: char| c.is_whitespace() || c == ';')
+                .filter(|token| !token.is_empty());
+
+            let qualifier = tokens.next();
+            let type_name = tokens.next();
+            let name = tokens.next();
+
+            if let (Some("uniform"), Some(block)) = (qualifier, type_name) {
+                if glsl_type(block).is_none() && name.map(|name| name.starts_with('{')).unwrap_or(true) {
+                    if !uniform_blocks.iter().any(|name| name == block) {
+                        uniform_blocks.push(block.to_string());
+                    }
+                    continue;
+                }
+            }
+
+            let (type_name, name) = match (type_name, name) {
+                (Some(type_name), Some(name)) => (type_name, name),
+                _ => continue,
+            };
+            let type_ = match glsl_type(type_name) {
+                Some(type_) => type_,
+                None => continue,
+            };
+            let (name, size) = split_array(name);
+
+            match qualifier {
+                Some("uniform") if !uniforms.iter().any(|uniform| uniform.name == name) => {
+                    let location = uniforms.last().map(|uniform| uniform.location + uniform.size).unwrap_or(0);
+                    uniforms.push(ActiveVariable { name, location, type_, size });
+                },
+                Some("in") if *kind == gl::VERTEX_SHADER => {
+                    let location = location.unwrap_or(attributes.len() as gl::types::GLint);
+                    attributes.push(ActiveVariable { name, location, type_, size });
+                },
+                _ => {}
+            }
+        }
+    }
+
+    Reflection { uniforms, attributes, uniform_blocks }
+}
+
+pub fn glsl_type(name: &str) -> Option<gl::types::GLenum> {
+    let type_ = match name {
+        "float" => gl::FLOAT,
+        "vec2" => gl::FLOAT_VEC2,
+        "vec3" => gl::FLOAT_VEC3,
+        "vec4" => gl::FLOAT_VEC4,
+        "int" => gl::INT,
+        "ivec2" => gl::INT_VEC2,
+        "ivec3" => gl::INT_VEC3,
+        "ivec4" => gl::INT_VEC4,
+        "bool" => gl::BOOL,
+        "mat3" => gl::FLOAT_MAT3,
+        "mat4" => gl::FLOAT_MAT4,
+        "sampler2D" => gl::SAMPLER_2D,
+        _ => return None,
+    };
+
+    Some(type_)
+}
+
+fn active_lines(source: &str) -> Vec<String> {
+    let mut defines = HashSet::new();
+    // one entry per open conditional: (branch taken, enclosing block active)
+    let mut stack: Vec<(bool, bool)> = vec![];
+    let mut lines = vec![];
+
+    for line in source.lines() {
+        let line = match line.find("//") {
+            Some(comment) => &line[..comment],
+            None => line,
+        }.trim();
+        let active = stack.last().map(|(taken, parent)| *taken && *parent).unwrap_or(true);
+
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("#define") if active => {
+                if let Some(name) = words.next() {
+                    defines.insert(name.to_string());
+                }
+            },
+            Some("#ifdef") => stack.push((words.next().map(|name| defines.contains(name)).unwrap_or(false), active)),
+            Some("#ifndef") => stack.push((!words.next().map(|name| defines.contains(name)).unwrap_or(false), active)),
+            Some("#if") => stack.push((true, active)),
+            Some("#else") => {
+                if let Some(top) = stack.last_mut() {
+                    top.0 = !top.0;
+                }
+            },
+            Some("#endif") => {
+                stack.pop();
+            },
+            _ if active && !line.is_empty() && !line.starts_with('#') => lines.push(line.to_string()),
+            _ => {}
+        }
+    }
+
+    lines
+}
+
+fn split_layout(line: &str) -> (Option<gl::types::GLint>, &str) {
+    if !line.starts_with("layout") {
+        return (None, line);
+    }
+
+    match (line.find('('), line.find(')')) {
+        (Some(open), Some(close)) if open < close => {
+            let location = line[open + 1..close].split(',')
+                .filter_map(|qualifier| qualifier.split_once('='))
+                .find(|(key, _)| key.trim() == "location")
+                .and_then(|(_, value)| value.trim().parse().ok());
+            (location, line[close + 1..].trim())
+        },
+        _ => (None, line),
+    }
+}
+
+fn split_array(name: &str) -> (String, gl::types::GLint) {
+    match name.find('[') {
+        Some(open) => {
+            let size = name[open + 1..].trim_end_matches(']').parse().unwrap_or(1);
+            (name[..open].to_string(), size)
+        },
+        None => (name.to_string(), 1),
+    }
+}
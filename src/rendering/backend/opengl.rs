@@ -0,0 +1,271 @@
+use std::any::Any;
+use std::ffi::CStr;
+use gl;
+use crate::rendering::{buffer, texture, shader, framebuffer, RenderError, ActiveVariable, UniformValue, TextureDescriptor, VertexAttribute};
+use super::{RenderBackend, ReleaseQueue};
+
+/// Backend that issues the calls on the current OpenGL context.
+#[derive(Default, Debug)]
+pub struct GlBackend {
+    release_queue: ReleaseQueue,
+}
+
+impl GlBackend {
+    pub fn new() -> Self {
+        GlBackend::default()
+    }
+}
+
+impl RenderBackend for GlBackend {
+    fn release_queue(&self) -> ReleaseQueue {
+        self.release_queue.clone()
+    }
+
+    fn delete_shader(&mut self, shader: gl::types::GLuint) {
+        shader::delete_shader(shader);
+    }
+
+    fn delete_program(&mut self, program: gl::types::GLuint) {
+        shader::delete_program(program);
+    }
+
+    fn delete_buffer(&mut self, index: gl::types::GLuint) {
+        buffer::delete_buffer(index);
+    }
+
+    fn delete_vertex_array(&mut self, vao: gl::types::GLuint) {
+        buffer::delete_vertex_array(vao);
+    }
+
+    fn delete_texture(&mut self, texture: gl::types::GLuint) {
+        texture::delete_texture(texture);
+    }
+
+    fn delete_framebuffer(&mut self, framebuffer: gl::types::GLuint) {
+        framebuffer::delete_framebuffer(framebuffer);
+    }
+
+    fn delete_renderbuffer(&mut self, renderbuffer: gl::types::GLuint) {
+        framebuffer::delete_renderbuffer(renderbuffer);
+    }
+
+    fn viewport(&mut self, x: i32, y: i32, width: i32, height: i32) {
+        unsafe {
+            gl::Viewport(x, y, width, height);
+        }
+    }
+
+    fn clear_color(&mut self, r: f32, g: f32, b: f32, a: f32) {
+        unsafe {
+            gl::ClearColor(r, g, b, a);
+        }
+    }
+
+    fn clear(&mut self, mask: gl::types::GLbitfield) {
+        unsafe {
+            gl::Clear(mask);
+        }
+    }
+
+    fn enable_blend(&mut self, src: gl::types::GLenum, dst: gl::types::GLenum) {
+        unsafe {
+            gl::Enable(gl::BLEND);
+            gl::BlendFunc(src, dst);
+        }
+    }
+
+    fn new_buffer(&mut self, data: &[u8], target: gl::types::GLenum, usage: gl::types::GLenum) -> Result<gl::types::GLuint, RenderError> {
+        buffer::new_buffer(data, target, usage)
+    }
+
+    fn buffer_data(&mut self, target: gl::types::GLenum, index: gl::types::GLuint, data: &[u8], usage: gl::types::GLenum) {
+        buffer::buffer_data(target, index, data, usage);
+    }
+
+    fn bind_buffer(&mut self, target: gl::types::GLenum, index: gl::types::GLuint) {
+        buffer::bind_buffer(target, index);
+    }
+
+    fn unbind_buffer(&mut self, target: gl::types::GLenum) {
+        buffer::unbind_buffer(target);
+    }
+
+    fn buffer_sub_data(&mut self, target: gl::types::GLenum, index: gl::types::GLuint, offset: usize, data: &[u8]) {
+        buffer::buffer_sub_data(target, index, offset, data);
+    }
+
+    fn bind_buffer_base(&mut self, target: gl::types::GLenum, binding: gl::types::GLuint, index: gl::types::GLuint) {
+        buffer::bind_buffer_base(target, binding, index);
+    }
+
+    fn new_vertex_array(&mut self) -> Result<gl::types::GLuint, RenderError> {
+        buffer::new_vertex_array()
+    }
+
+    fn bind_vertex_array(&mut self, vao: gl::types::GLuint) {
+        buffer::bind_vertex_array(vao);
+    }
+
+    fn unbind_vertex_array(&mut self) {
+        buffer::unbind_vertex_array();
+    }
+
+    fn vertex_attrib_pointer(&mut self, buffer_index: gl::types::GLuint, attribute: &VertexAttribute,
+        stride: gl::types::GLsizei, offset: usize) {
+        let normalized = if attribute.normalized { gl::TRUE } else { gl::FALSE };
+        buffer::vertex_attrib_pointer(buffer_index, attribute.location, attribute.count as gl::types::GLint,
+            attribute.type_, normalized, stride, offset);
+    }
+
+    fn vertex_attrib_divisor(&mut self, index: gl::types::GLuint, divisor: gl::types::GLuint) {
+        buffer::vertex_attrib_divisor(index, divisor);
+    }
+
+    fn gen_texture(&mut self) -> Result<gl::types::GLuint, RenderError> {
+        texture::gen_texture()
+    }
+
+    fn bind_texture(&mut self, texture: gl::types::GLuint) {
+        texture::bind_texture(texture);
+    }
+
+    fn bind_texture_unit(&mut self, active_texture: gl::types::GLenum, texture: gl::types::GLuint) {
+        texture::bind_texture_unit(active_texture, texture);
+    }
+
+    fn unbind_texture(&mut self) {
+        texture::unbind_texture();
+    }
+
+    fn set_texture_sampling(&mut self, descriptor: &TextureDescriptor) {
+        texture::set_texture_sampling(descriptor);
+    }
+
+    fn generate_mipmap(&mut self) {
+        texture::generate_mipmap();
+    }
+
+    fn set_texture_swizzle(&mut self, swizzle: [gl::types::GLenum; 4]) {
+        texture::set_texture_swizzle(swizzle);
+    }
+
+    fn set_pixel_store_mode(&mut self, mode: gl::types::GLenum, value: gl::types::GLint) {
+        texture::set_pixel_store_mode(mode, value);
+    }
+
+    fn set_texture_2d(&mut self, internal_format: gl::types::GLenum, mode: gl::types::GLenum, width: i32, height: i32, data_type: gl::types::GLenum, data: &[u8]) {
+        texture::set_texture_2d(internal_format, mode, width, height, data_type, data);
+    }
+
+    fn new_framebuffer(&mut self) -> Result<gl::types::GLuint, RenderError> {
+        framebuffer::new_framebuffer()
+    }
+
+    fn bind_framebuffer(&mut self, framebuffer: gl::types::GLuint) {
+        framebuffer::bind_framebuffer(framebuffer);
+    }
+
+    fn framebuffer_texture(&mut self, attachment: gl::types::GLenum, texture: gl::types::GLuint) {
+        framebuffer::framebuffer_texture(attachment, texture);
+    }
+
+    fn new_renderbuffer(&mut self, internal_format: gl::types::GLenum, width: i32, height: i32) -> Result<gl::types::GLuint, RenderError> {
+        framebuffer::new_renderbuffer(internal_format, width, height)
+    }
+
+    fn framebuffer_renderbuffer(&mut self, attachment: gl::types::GLenum, renderbuffer: gl::types::GLuint) {
+        framebuffer::framebuffer_renderbuffer(attachment, renderbuffer);
+    }
+
+    fn check_framebuffer_status(&mut self) -> gl::types::GLenum {
+        framebuffer::check_framebuffer_status()
+    }
+
+    fn read_pixels(&mut self, x: i32, y: i32, width: i32, height: i32, data: &mut [u8]) {
+        framebuffer::read_pixels(x, y, width, height, data);
+    }
+
+    fn shader_from_source(&mut self, name: &str, source: &CStr, kind: gl::types::GLenum) -> Result<gl::types::GLuint, RenderError> {
+        shader::shader_from_source(name, source, kind)
+    }
+
+    fn create_program(&mut self, name: &str, shaders: [gl::types::GLuint; 2]) -> Result<gl::types::GLuint, RenderError> {
+        shader::create_program(name, shaders)
+    }
+
+    fn use_program(&mut self, program: gl::types::GLuint) {
+        shader::use_program(program);
+    }
+
+    fn get_uniform_location(&mut self, program: gl::types::GLuint, name: &str) -> Result<gl::types::GLint, RenderError> {
+        shader::get_uniform_location(program, name)
+    }
+
+    fn active_uniforms(&mut self, program: gl::types::GLuint) -> Vec<ActiveVariable> {
+        shader::get_active_uniforms(program)
+    }
+
+    fn active_attributes(&mut self, program: gl::types::GLuint) -> Vec<ActiveVariable> {
+        shader::get_active_attributes(program)
+    }
+
+    fn active_uniform_blocks(&mut self, program: gl::types::GLuint) -> Vec<String> {
+        shader::get_active_uniform_blocks(program)
+    }
+
+    fn uniform_block_binding(&mut self, program: gl::types::GLuint, name: &str, binding: gl::types::GLuint) -> Result<(), RenderError> {
+        shader::uniform_block_binding(program, name, binding)
+    }
+
+    fn set_uniform(&mut self, location: gl::types::GLint, value: &UniformValue) {
+        let count = value.len() as gl::types::GLsizei;
+        unsafe {
+            match value {
+                UniformValue::Float(v) => gl::Uniform1fv(location, count, v.as_ptr()),
+                UniformValue::Vec2(v) => gl::Uniform2fv(location, count, v.as_ptr() as *const f32),
+                UniformValue::Vec3(v) => gl::Uniform3fv(location, count, v.as_ptr() as *const f32),
+                UniformValue::Vec4(v) => gl::Uniform4fv(location, count, v.as_ptr() as *const f32),
+                UniformValue::Int(v) => gl::Uniform1iv(location, count, v.as_ptr()),
+                UniformValue::Bool(v) => {
+                    let ints: Vec<i32> = v.iter().map(|b| *b as i32).collect();
+                    gl::Uniform1iv(location, count, ints.as_ptr());
+                },
+                UniformValue::Mat3(v) => gl::UniformMatrix3fv(location, count, gl::FALSE, v.as_ptr() as *const f32),
+                UniformValue::Mat4(v) => gl::UniformMatrix4fv(location, count, gl::FALSE, v.as_ptr() as *const f32),
+                UniformValue::Sampler(v) => {
+                    let units: Vec<i32> = v.iter().map(|unit| unit.0 as i32).collect();
+                    gl::Uniform1iv(location, count, units.as_ptr());
+                },
+            }
+        }
+    }
+
+    fn draw_elements(&mut self, mode: gl::types::GLenum, count: i32, index_type: gl::types::GLenum, offset: usize) {
+        unsafe {
+            // `offset` is in bytes into the bound element buffer
+            gl::DrawElements(mode, count, index_type, offset as *const gl::types::GLvoid);
+        }
+    }
+
+    fn draw_elements_instanced(&mut self, mode: gl::types::GLenum, count: i32, index_type: gl::types::GLenum, offset: usize, instances: i32) {
+        unsafe {
+            gl::DrawElementsInstanced(mode, count, index_type, offset as *const gl::types::GLvoid, instances);
+        }
+    }
+
+    fn draw_arrays(&mut self, mode: gl::types::GLenum, first: i32, count: i32) {
+        unsafe {
+            gl::DrawArrays(mode, first, count);
+        }
+    }
+
+    fn draw_arrays_instanced(&mut self, mode: gl::types::GLenum, first: i32, count: i32, instances: i32) {
+        unsafe {
+            gl::DrawArraysInstanced(mode, first, count, instances);
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
@@ -0,0 +1,383 @@
+use std::any::Any;
+use std::collections::HashMap;
+use std::ffi::CStr;
+use std::sync::{Arc, Mutex};
+use gl;
+use crate::rendering::{RenderError, ActiveVariable, UniformValue, TextureDescriptor, VertexAttribute};
+use super::{RenderBackend, ReleaseQueue, glsl};
+use super::glsl::Reflection;
+
+/// One call made against a `RecordingBackend`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RenderCommand {
+    DeleteShader { shader: gl::types::GLuint },
+    DeleteProgram { program: gl::types::GLuint },
+    DeleteBuffer { index: gl::types::GLuint },
+    DeleteVertexArray { vao: gl::types::GLuint },
+    DeleteTexture { texture: gl::types::GLuint },
+    DeleteFramebuffer { framebuffer: gl::types::GLuint },
+    DeleteRenderbuffer { renderbuffer: gl::types::GLuint },
+    Viewport { x: i32, y: i32, width: i32, height: i32 },
+    ClearColor { r: f32, g: f32, b: f32, a: f32 },
+    Clear { mask: gl::types::GLbitfield },
+    EnableBlend { src: gl::types::GLenum, dst: gl::types::GLenum },
+    NewBuffer { index: gl::types::GLuint, target: gl::types::GLenum, usage: gl::types::GLenum, data: Vec<u8> },
+    BindBuffer { target: gl::types::GLenum, index: gl::types::GLuint },
+    UnbindBuffer { target: gl::types::GLenum },
+    BufferData { target: gl::types::GLenum, index: gl::types::GLuint, usage: gl::types::GLenum, data: Vec<u8> },
+    BufferSubData { target: gl::types::GLenum, index: gl::types::GLuint, offset: usize, data: Vec<u8> },
+    BindBufferBase { target: gl::types::GLenum, binding: gl::types::GLuint, index: gl::types::GLuint },
+    NewVertexArray { vao: gl::types::GLuint },
+    BindVertexArray { vao: gl::types::GLuint },
+    UnbindVertexArray,
+    VertexAttribPointer {
+        buffer_index: gl::types::GLuint,
+        attribute: VertexAttribute,
+        stride: gl::types::GLsizei,
+        offset: usize
+    },
+    VertexAttribDivisor { index: gl::types::GLuint, divisor: gl::types::GLuint },
+    GenTexture { texture: gl::types::GLuint },
+    BindTexture { texture: gl::types::GLuint },
+    BindTextureUnit { active_texture: gl::types::GLenum, texture: gl::types::GLuint },
+    UnbindTexture,
+    SetTextureSampling { descriptor: TextureDescriptor },
+    GenerateMipmap,
+    SetTextureSwizzle { swizzle: [gl::types::GLenum; 4] },
+    SetPixelStoreMode { mode: gl::types::GLenum, value: gl::types::GLint },
+    SetTexture2d { internal_format: gl::types::GLenum, mode: gl::types::GLenum, width: i32, height: i32, data_type: gl::types::GLenum, len: usize },
+    NewFramebuffer { framebuffer: gl::types::GLuint },
+    BindFramebuffer { framebuffer: gl::types::GLuint },
+    FramebufferTexture { attachment: gl::types::GLenum, texture: gl::types::GLuint },
+    NewRenderbuffer { renderbuffer: gl::types::GLuint, internal_format: gl::types::GLenum, width: i32, height: i32 },
+    ReadPixels { x: i32, y: i32, width: i32, height: i32 },
+    FramebufferRenderbuffer { attachment: gl::types::GLenum, renderbuffer: gl::types::GLuint },
+    ShaderFromSource { shader: gl::types::GLuint, name: String, kind: gl::types::GLenum, source: String },
+    CreateProgram { program: gl::types::GLuint, name: String, shaders: [gl::types::GLuint; 2] },
+    UseProgram { program: gl::types::GLuint },
+    UniformBlockBinding { program: gl::types::GLuint, name: String, binding: gl::types::GLuint },
+    GetUniformLocation { program: gl::types::GLuint, name: String, location: gl::types::GLint },
+    Uniform { program: gl::types::GLuint, name: String, value: UniformValue<'static> },
+    DrawElements { mode: gl::types::GLenum, count: i32, index_type: gl::types::GLenum, offset: usize },
+    DrawElementsInstanced { mode: gl::types::GLenum, count: i32, index_type: gl::types::GLenum, offset: usize, instances: i32 },
+    DrawArrays { mode: gl::types::GLenum, first: i32, count: i32 },
+    DrawArraysInstanced { mode: gl::types::GLenum, first: i32, count: i32, instances: i32 },
+}
+
+/// Shared view of the commands recorded by a `RecordingBackend`. Keep a clone
+/// before handing the backend over to the world to inspect it afterwards.
+#[derive(Default, Debug, Clone)]
+pub struct CommandLog(Arc<Mutex<Vec<RenderCommand>>>);
+
+impl CommandLog {
+    pub fn push(&self, command: RenderCommand) {
+        self.0.lock().unwrap().push(command);
+    }
+
+    pub fn commands(&self) -> Vec<RenderCommand> {
+        self.0.lock().unwrap().clone()
+    }
+
+    pub fn draw_count(&self) -> usize {
+        self.0.lock().unwrap().iter()
+            .filter(|command| matches!(command, RenderCommand::DrawElements { .. } | RenderCommand::DrawElementsInstanced { .. }
+                | RenderCommand::DrawArrays { .. } | RenderCommand::DrawArraysInstanced { .. }))
+            .count()
+    }
+
+    pub fn clear(&self) {
+        self.0.lock().unwrap().clear();
+    }
+}
+
+/// Backend that never touches a GPU. Object names are handed out from a counter
+/// and every call is appended to the `CommandLog`. Programs are reflected from the
+/// GLSL source with `glsl::reflect`.
+#[derive(Default, Debug)]
+pub struct RecordingBackend {
+    log: CommandLog,
+    release_queue: ReleaseQueue,
+    next_name: gl::types::GLuint,
+    current_program: gl::types::GLuint,
+    shaders: HashMap<gl::types::GLuint, (gl::types::GLenum, String)>,
+    programs: HashMap<gl::types::GLuint, Reflection>,
+}
+
+impl RecordingBackend {
+    pub fn new() -> Self {
+        RecordingBackend::default()
+    }
+
+    pub fn log(&self) -> CommandLog {
+        self.log.clone()
+    }
+
+    fn gen_name(&mut self) -> gl::types::GLuint {
+        self.next_name += 1;
+        self.next_name
+    }
+
+    fn uniform_name(&self, location: gl::types::GLint) -> String {
+        self.programs.get(&self.current_program)
+            .and_then(|reflection| reflection.uniforms.iter().find(|uniform| uniform.location == location))
+            .map(|uniform| uniform.name.clone())
+            .unwrap_or_default()
+    }
+}
+
+impl RenderBackend for RecordingBackend {
+    fn release_queue(&self) -> ReleaseQueue {
+        self.release_queue.clone()
+    }
+
+    fn delete_shader(&mut self, shader: gl::types::GLuint) {
+        self.log.push(RenderCommand::DeleteShader { shader });
+    }
+
+    fn delete_program(&mut self, program: gl::types::GLuint) {
+        self.log.push(RenderCommand::DeleteProgram { program });
+    }
+
+    fn delete_buffer(&mut self, index: gl::types::GLuint) {
+        self.log.push(RenderCommand::DeleteBuffer { index });
+    }
+
+    fn delete_vertex_array(&mut self, vao: gl::types::GLuint) {
+        self.log.push(RenderCommand::DeleteVertexArray { vao });
+    }
+
+    fn delete_texture(&mut self, texture: gl::types::GLuint) {
+        self.log.push(RenderCommand::DeleteTexture { texture });
+    }
+
+    fn delete_framebuffer(&mut self, framebuffer: gl::types::GLuint) {
+        self.log.push(RenderCommand::DeleteFramebuffer { framebuffer });
+    }
+
+    fn delete_renderbuffer(&mut self, renderbuffer: gl::types::GLuint) {
+        self.log.push(RenderCommand::DeleteRenderbuffer { renderbuffer });
+    }
+
+    fn viewport(&mut self, x: i32, y: i32, width: i32, height: i32) {
+        self.log.push(RenderCommand::Viewport { x, y, width, height });
+    }
+
+    fn clear_color(&mut self, r: f32, g: f32, b: f32, a: f32) {
+        self.log.push(RenderCommand::ClearColor { r, g, b, a });
+    }
+
+    fn clear(&mut self, mask: gl::types::GLbitfield) {
+        self.log.push(RenderCommand::Clear { mask });
+    }
+
+    fn enable_blend(&mut self, src: gl::types::GLenum, dst: gl::types::GLenum) {
+        self.log.push(RenderCommand::EnableBlend { src, dst });
+    }
+
+    fn new_buffer(&mut self, data: &[u8], target: gl::types::GLenum, usage: gl::types::GLenum) -> Result<gl::types::GLuint, RenderError> {
+        let index = self.gen_name();
+        self.log.push(RenderCommand::NewBuffer { index, target, usage, data: data.to_vec() });
+        Ok(index)
+    }
+
+    fn buffer_data(&mut self, target: gl::types::GLenum, index: gl::types::GLuint, data: &[u8], usage: gl::types::GLenum) {
+        self.log.push(RenderCommand::BufferData { target, index, usage, data: data.to_vec() });
+    }
+
+    fn bind_buffer(&mut self, target: gl::types::GLenum, index: gl::types::GLuint) {
+        self.log.push(RenderCommand::BindBuffer { target, index });
+    }
+
+    fn unbind_buffer(&mut self, target: gl::types::GLenum) {
+        self.log.push(RenderCommand::UnbindBuffer { target });
+    }
+
+    fn buffer_sub_data(&mut self, target: gl::types::GLenum, index: gl::types::GLuint, offset: usize, data: &[u8]) {
+        self.log.push(RenderCommand::BufferSubData { target, index, offset, data: data.to_vec() });
+    }
+
+    fn bind_buffer_base(&mut self, target: gl::types::GLenum, binding: gl::types::GLuint, index: gl::types::GLuint) {
+        self.log.push(RenderCommand::BindBufferBase { target, binding, index });
+    }
+
+    fn new_vertex_array(&mut self) -> Result<gl::types::GLuint, RenderError> {
+        let vao = self.gen_name();
+        self.log.push(RenderCommand::NewVertexArray { vao });
+        Ok(vao)
+    }
+
+    fn bind_vertex_array(&mut self, vao: gl::types::GLuint) {
+        self.log.push(RenderCommand::BindVertexArray { vao });
+    }
+
+    fn unbind_vertex_array(&mut self) {
+        self.log.push(RenderCommand::UnbindVertexArray);
+    }
+
+    fn vertex_attrib_pointer(&mut self, buffer_index: gl::types::GLuint, attribute: &VertexAttribute,
+        stride: gl::types::GLsizei, offset: usize) {
+        self.log.push(RenderCommand::VertexAttribPointer { buffer_index, attribute: attribute.clone(), stride, offset });
+    }
+
+    fn vertex_attrib_divisor(&mut self, index: gl::types::GLuint, divisor: gl::types::GLuint) {
+        self.log.push(RenderCommand::VertexAttribDivisor { index, divisor });
+    }
+
+    fn gen_texture(&mut self) -> Result<gl::types::GLuint, RenderError> {
+        let texture = self.gen_name();
+        self.log.push(RenderCommand::GenTexture { texture });
+        Ok(texture)
+    }
+
+    fn bind_texture(&mut self, texture: gl::types::GLuint) {
+        self.log.push(RenderCommand::BindTexture { texture });
+    }
+
+    fn bind_texture_unit(&mut self, active_texture: gl::types::GLenum, texture: gl::types::GLuint) {
+        self.log.push(RenderCommand::BindTextureUnit { active_texture, texture });
+    }
+
+    fn unbind_texture(&mut self) {
+        self.log.push(RenderCommand::UnbindTexture);
+    }
+
+    fn set_texture_sampling(&mut self, descriptor: &TextureDescriptor) {
+        self.log.push(RenderCommand::SetTextureSampling { descriptor: *descriptor });
+    }
+
+    fn generate_mipmap(&mut self) {
+        self.log.push(RenderCommand::GenerateMipmap);
+    }
+
+    fn set_texture_swizzle(&mut self, swizzle: [gl::types::GLenum; 4]) {
+        self.log.push(RenderCommand::SetTextureSwizzle { swizzle });
+    }
+
+    fn set_pixel_store_mode(&mut self, mode: gl::types::GLenum, value: gl::types::GLint) {
+        self.log.push(RenderCommand::SetPixelStoreMode { mode, value });
+    }
+
+    fn set_texture_2d(&mut self, internal_format: gl::types::GLenum, mode: gl::types::GLenum, width: i32, height: i32, data_type: gl::types::GLenum, data: &[u8]) {
+        self.log.push(RenderCommand::SetTexture2d { internal_format, mode, width, height, data_type, len: data.len() });
+    }
+
+    fn new_framebuffer(&mut self) -> Result<gl::types::GLuint, RenderError> {
+        let framebuffer = self.gen_name();
+        self.log.push(RenderCommand::NewFramebuffer { framebuffer });
+        Ok(framebuffer)
+    }
+
+    fn bind_framebuffer(&mut self, framebuffer: gl::types::GLuint) {
+        self.log.push(RenderCommand::BindFramebuffer { framebuffer });
+    }
+
+    fn framebuffer_texture(&mut self, attachment: gl::types::GLenum, texture: gl::types::GLuint) {
+        self.log.push(RenderCommand::FramebufferTexture { attachment, texture });
+    }
+
+    fn new_renderbuffer(&mut self, internal_format: gl::types::GLenum, width: i32, height: i32) -> Result<gl::types::GLuint, RenderError> {
+        let renderbuffer = self.gen_name();
+        self.log.push(RenderCommand::NewRenderbuffer { renderbuffer, internal_format, width, height });
+        Ok(renderbuffer)
+    }
+
+    fn framebuffer_renderbuffer(&mut self, attachment: gl::types::GLenum, renderbuffer: gl::types::GLuint) {
+        self.log.push(RenderCommand::FramebufferRenderbuffer { attachment, renderbuffer });
+    }
+
+    fn check_framebuffer_status(&mut self) -> gl::types::GLenum {
+        gl::FRAMEBUFFER_COMPLETE
+    }
+
+    fn read_pixels(&mut self, x: i32, y: i32, width: i32, height: i32, data: &mut [u8]) {
+        data.iter_mut().for_each(|byte| *byte = 0);
+        self.log.push(RenderCommand::ReadPixels { x, y, width, height });
+    }
+
+    fn shader_from_source(&mut self, name: &str, source: &CStr, kind: gl::types::GLenum) -> Result<gl::types::GLuint, RenderError> {
+        let shader = self.gen_name();
+        let source = source.to_string_lossy().into_owned();
+        self.shaders.insert(shader, (kind, source.clone()));
+        self.log.push(RenderCommand::ShaderFromSource { shader, name: name.to_string(), kind, source });
+        Ok(shader)
+    }
+
+    fn create_program(&mut self, name: &str, shaders: [gl::types::GLuint; 2]) -> Result<gl::types::GLuint, RenderError> {
+        let program = self.gen_name();
+        let stages: Vec<(gl::types::GLenum, &str)> = shaders.iter()
+            .filter_map(|shader| self.shaders.get(shader))
+            .map(|(kind, source)| (*kind, source.as_str()))
+            .collect();
+        let reflection = glsl::reflect(&stages);
+        self.programs.insert(program, reflection);
+        self.log.push(RenderCommand::CreateProgram { program, name: name.to_string(), shaders });
+        Ok(program)
+    }
+
+    fn use_program(&mut self, program: gl::types::GLuint) {
+        self.current_program = program;
+        self.log.push(RenderCommand::UseProgram { program });
+    }
+
+    fn get_uniform_location(&mut self, program: gl::types::GLuint, name: &str) -> Result<gl::types::GLint, RenderError> {
+        let location = self.programs.get(&program)
+            .and_then(|reflection| reflection.uniforms.iter().find(|uniform| uniform.name == name))
+            .map(|uniform| uniform.location)
+            .unwrap_or(-1);
+        self.log.push(RenderCommand::GetUniformLocation { program, name: name.to_string(), location });
+
+        if location == -1 {
+            return Err(RenderError::MissingUniform { program, name: name.to_string() });
+        }
+        Ok(location)
+    }
+
+    fn active_uniforms(&mut self, program: gl::types::GLuint) -> Vec<ActiveVariable> {
+        self.programs.get(&program).map(|reflection| reflection.uniforms.clone()).unwrap_or_default()
+    }
+
+    fn active_attributes(&mut self, program: gl::types::GLuint) -> Vec<ActiveVariable> {
+        self.programs.get(&program).map(|reflection| reflection.attributes.clone()).unwrap_or_default()
+    }
+
+    fn active_uniform_blocks(&mut self, program: gl::types::GLuint) -> Vec<String> {
+        self.programs.get(&program).map(|reflection| reflection.uniform_blocks.clone()).unwrap_or_default()
+    }
+
+    fn uniform_block_binding(&mut self, program: gl::types::GLuint, name: &str, binding: gl::types::GLuint) -> Result<(), RenderError> {
+        let found = self.programs.get(&program)
+            .map(|reflection| reflection.uniform_blocks.iter().any(|block| block == name))
+            .unwrap_or(false);
+        self.log.push(RenderCommand::UniformBlockBinding { program, name: name.to_string(), binding });
+
+        if !found {
+            return Err(RenderError::MissingUniform { program, name: name.to_string() });
+        }
+        Ok(())
+    }
+
+    fn set_uniform(&mut self, location: gl::types::GLint, value: &UniformValue) {
+        let name = self.uniform_name(location);
+        self.log.push(RenderCommand::Uniform { program: self.current_program, name, value: value.clone().into_owned() });
+    }
+
+    fn draw_elements(&mut self, mode: gl::types::GLenum, count: i32, index_type: gl::types::GLenum, offset: usize) {
+        self.log.push(RenderCommand::DrawElements { mode, count, index_type, offset });
+    }
+
+    fn draw_elements_instanced(&mut self, mode: gl::types::GLenum, count: i32, index_type: gl::types::GLenum, offset: usize, instances: i32) {
+        self.log.push(RenderCommand::DrawElementsInstanced { mode, count, index_type, offset, instances });
+    }
+
+    fn draw_arrays(&mut self, mode: gl::types::GLenum, first: i32, count: i32) {
+        self.log.push(RenderCommand::DrawArrays { mode, first, count });
+    }
+
+    fn draw_arrays_instanced(&mut self, mode: gl::types::GLenum, first: i32, count: i32, instances: i32) {
+        self.log.push(RenderCommand::DrawArraysInstanced { mode, first, count, instances });
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
@@ -0,0 +1,953 @@
+use std::any::Any;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::ffi::CStr;
+use gl;
+use glm::{vec4, Mat4, Vec2, Vec4};
+use crate::rendering::{RenderError, ActiveVariable, UniformValue, TextureUnit, TextureDescriptor, VertexAttribute, Image, FRAME_DATA_BLOCK};
+use crate::rendering::vertex_layout::{component_size, decode_component};
+use super::{RenderBackend, ReleaseQueue, glsl};
+use super::glsl::Reflection;
+
+#[derive(Default, Debug, Clone, Copy)]
+struct AttribPointer {
+    buffer: gl::types::GLuint,
+    size: usize,
+    type_: gl::types::GLenum,
+    normalized: bool,
+    stride: usize,
+    offset: usize,
+    divisor: usize,
+}
+
+/// Texels are kept as linear RGBA floats, row 0 at `v = 0` like GL.
+#[derive(Default, Debug, Clone)]
+struct SoftTexture {
+    width: usize,
+    height: usize,
+    texels: Vec<Vec4>,
+    descriptor: TextureDescriptor,
+    swizzle: Option<[gl::types::GLenum; 4]>,
+    srgb: bool,
+}
+
+impl SoftTexture {
+    fn texel(&self, x: i64, y: i64) -> Vec4 {
+        let x = wrap(x, self.width, self.descriptor.wrap_s);
+        let y = wrap(y, self.height, self.descriptor.wrap_t);
+        let texel = self.texels[y * self.width + x];
+
+        match self.swizzle {
+            Some(swizzle) => {
+                let channel = |source| match source {
+                    gl::RED => texel.x,
+                    gl::GREEN => texel.y,
+                    gl::BLUE => texel.z,
+                    gl::ALPHA => texel.w,
+                    gl::ONE => 1.0,
+                    _ => 0.0,
+                };
+                vec4(channel(swizzle[0]), channel(swizzle[1]), channel(swizzle[2]), channel(swizzle[3]))
+            },
+            None => texel,
+        }
+    }
+
+    fn sample(&self, uv: Vec2) -> Vec4 {
+        if self.width == 0 || self.height == 0 {
+            return vec4(0., 0., 0., 1.);
+        }
+
+        // no mip levels are kept, so magnification rules apply everywhere
+        let x = uv.x * self.width as f32 - 0.5;
+        let y = uv.y * self.height as f32 - 0.5;
+
+        if self.descriptor.mag_filter == gl::LINEAR {
+            let (x0, y0) = (x.floor(), y.floor());
+            let (fx, fy) = (x - x0, y - y0);
+            let (x0, y0) = (x0 as i64, y0 as i64);
+            let top = self.texel(x0, y0) * (1. - fx) + self.texel(x0 + 1, y0) * fx;
+            let bottom = self.texel(x0, y0 + 1) * (1. - fx) + self.texel(x0 + 1, y0 + 1) * fx;
+            top * (1. - fy) + bottom * fy
+        } else {
+            self.texel(x.round() as i64, y.round() as i64)
+        }
+    }
+}
+
+fn wrap(coord: i64, size: usize, mode: gl::types::GLenum) -> usize {
+    let size = size as i64;
+    let wrapped = match mode {
+        gl::REPEAT => coord.rem_euclid(size),
+        gl::MIRRORED_REPEAT => {
+            let period = coord.rem_euclid(size * 2);
+            if period < size { period } else { size * 2 - 1 - period }
+        },
+        _ => coord.clamp(0, size - 1),
+    };
+    wrapped as usize
+}
+
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 { value / 12.92 } else { ((value + 0.055) / 1.055).powf(2.4) }
+}
+
+/// Which of the built-in shaders a program behaves like, decided from its reflection.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Shading {
+    /// `triangle`: vertex colour, opaque
+    VertexColor,
+    /// `textured`; an `Offset` uniform, declared when built with `UV_OFFSET`, shifts the UVs
+    Textured,
+}
+
+#[derive(Debug, Clone)]
+struct SoftProgram {
+    reflection: Reflection,
+    shading: Shading,
+    uniforms: HashMap<gl::types::GLint, UniformValue<'static>>,
+    block_bindings: HashMap<String, gl::types::GLuint>,
+}
+
+impl SoftProgram {
+    fn uniform(&self, name: &str) -> Option<&UniformValue<'static>> {
+        let location = self.reflection.uniforms.iter().find(|uniform| uniform.name == name)?.location;
+        self.uniforms.get(&location)
+    }
+
+    fn attribute_location(&self, name: &str) -> Option<usize> {
+        self.reflection.attributes.iter()
+            .find(|attribute| attribute.name == name)
+            .map(|attribute| attribute.location as usize)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ShadedVertex {
+    position: Vec4,
+    tex_coord: Vec2,
+    color: Vec4,
+}
+
+/// Pure-Rust rasterizer that runs the render systems without a GPU, e.g. on CI. It understands
+/// the built-in `textured` (with or without `UV_OFFSET`), `instanced` and `triangle` shading rules rather
+/// than arbitrary GLSL, draws without depth testing, and keeps the window as an RGBA float image.
+#[derive(Debug)]
+pub struct SoftwareBackend {
+    release_queue: ReleaseQueue,
+    next_name: gl::types::GLuint,
+    width: usize,
+    height: usize,
+    window: Vec<Vec4>,
+
+    viewport: (i32, i32, i32, i32),
+    clear_color: Vec4,
+    blend: Option<(gl::types::GLenum, gl::types::GLenum)>,
+    unpack_alignment: usize,
+
+    buffers: HashMap<gl::types::GLuint, Vec<u8>>,
+    element_buffer: gl::types::GLuint,
+    uniform_bindings: HashMap<gl::types::GLuint, gl::types::GLuint>,
+    vertex_arrays: HashMap<gl::types::GLuint, HashMap<gl::types::GLuint, AttribPointer>>,
+    vertex_array: gl::types::GLuint,
+
+    textures: HashMap<gl::types::GLuint, SoftTexture>,
+    texture_units: [gl::types::GLuint; 16],
+    active_unit: usize,
+
+    framebuffers: HashMap<gl::types::GLuint, gl::types::GLuint>,
+    framebuffer: gl::types::GLuint,
+
+    shaders: HashMap<gl::types::GLuint, (gl::types::GLenum, String)>,
+    programs: HashMap<gl::types::GLuint, SoftProgram>,
+    current_program: gl::types::GLuint,
+}
+
+impl SoftwareBackend {
+    pub fn new(width: usize, height: usize) -> Self {
+        SoftwareBackend {
+            release_queue: ReleaseQueue::default(),
+            next_name: 0,
+            width,
+            height,
+            window: vec![vec4(0., 0., 0., 0.); width * height],
+            viewport: (0, 0, width as i32, height as i32),
+            clear_color: vec4(0., 0., 0., 0.),
+            blend: None,
+            unpack_alignment: 4,
+            buffers: HashMap::new(),
+            element_buffer: 0,
+            uniform_bindings: HashMap::new(),
+            vertex_arrays: HashMap::new(),
+            vertex_array: 0,
+            textures: HashMap::new(),
+            texture_units: [0; 16],
+            active_unit: 0,
+            framebuffers: HashMap::new(),
+            framebuffer: 0,
+            shaders: HashMap::new(),
+            programs: HashMap::new(),
+            current_program: 0,
+        }
+    }
+
+    /// The window contents, top row first.
+    pub fn window_image(&self) -> Image {
+        let mut pixels = Vec::with_capacity(self.width * self.height * 4);
+        for row in self.window.chunks(self.width.max(1)).rev() {
+            pixels.extend(row.iter().flat_map(|color| to_rgba8(*color)));
+        }
+
+        Image { width: self.width, height: self.height, pixels }
+    }
+
+    fn gen_name(&mut self) -> gl::types::GLuint {
+        self.next_name += 1;
+        self.next_name
+    }
+
+    fn bound_texture(&mut self) -> Option<&mut SoftTexture> {
+        let texture = self.texture_units[self.active_unit];
+        self.textures.get_mut(&texture)
+    }
+
+    /// Colour buffer of the bound framebuffer with its size.
+    fn target(&mut self) -> (&mut Vec<Vec4>, usize, usize) {
+        let attachment = self.framebuffers.get(&self.framebuffer).copied().unwrap_or(0);
+        match self.textures.get_mut(&attachment) {
+            Some(texture) if self.framebuffer != 0 => (&mut texture.texels, texture.width, texture.height),
+            _ => (&mut self.window, self.width, self.height),
+        }
+    }
+
+    fn fetch(&self, pointer: Option<&AttribPointer>, vertex: usize, instance: usize) -> Vec4 {
+        let mut value = vec4(0., 0., 0., 1.);
+        let pointer = match pointer {
+            Some(pointer) if pointer.size > 0 => pointer,
+            _ => return value,
+        };
+        let element = instance.checked_div(pointer.divisor).unwrap_or(vertex);
+        let data = match self.buffers.get(&pointer.buffer) {
+            Some(data) => data,
+            None => return value,
+        };
+
+        let size = component_size(pointer.type_);
+        let stride = if pointer.stride == 0 { pointer.size * size } else { pointer.stride };
+        for component in 0..pointer.size.min(4) {
+            let offset = element * stride + pointer.offset + component * size;
+            if let Some(bytes) = data.get(offset..offset + size) {
+                value[component] = decode_component(bytes, pointer.type_, pointer.normalized);
+            }
+        }
+        value
+    }
+
+    fn indices(&self, offset: usize, count: usize, index_type: gl::types::GLenum) -> Vec<usize> {
+        let data = match self.buffers.get(&self.element_buffer) {
+            Some(data) => data,
+            None => return vec![],
+        };
+
+        let size = match index_type {
+            gl::UNSIGNED_BYTE => 1,
+            gl::UNSIGNED_SHORT => 2,
+            _ => 4,
+        };
+        data.get(offset..).unwrap_or(&[]).chunks_exact(size).take(count)
+            .map(|bytes| match size {
+                1 => bytes[0] as usize,
+                2 => u16::from_ne_bytes([bytes[0], bytes[1]]) as usize,
+                _ => u32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize,
+            })
+            .collect()
+    }
+
+    fn frame_matrices(&self, program: &SoftProgram) -> Option<(Mat4, Mat4)> {
+        let binding = program.block_bindings.get(FRAME_DATA_BLOCK)?;
+        let data = self.buffers.get(self.uniform_bindings.get(binding)?)?;
+        let matrix = |offset: usize| -> Option<Mat4> {
+            let floats: Vec<f32> = data.get(offset..offset + 64)?
+                .chunks_exact(4)
+                .map(|bytes| f32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+                .collect();
+            Some(Mat4::from_column_slice(&floats))
+        };
+
+        Some((matrix(0)?, matrix(64)?))
+    }
+
+    fn shade_vertices(&self, program: &SoftProgram, indices: &[usize], instance: usize) -> Vec<ShadedVertex> {
+        let mat4 = |name| match program.uniform(name) {
+            Some(UniformValue::Mat4(value)) => value.first().copied(),
+            _ => None,
+        };
+        let transform = match (self.frame_matrices(program), mat4("Model"), mat4("MVPMatrix")) {
+            (Some((projection, view)), model, _) => projection * view * model.unwrap_or_else(Mat4::identity),
+            (None, _, Some(mvp)) => mvp,
+            _ => Mat4::identity(),
+        };
+
+        let attributes = self.vertex_arrays.get(&self.vertex_array);
+        let pointer = |name| program.attribute_location(name)
+            .and_then(|location| attributes.and_then(|attributes| attributes.get(&(location as gl::types::GLuint))));
+        let (position, tex_coord, color) = (pointer("Position"), pointer("TexCoord"), pointer("Color"));
+
+        // `instanced`: translation and rotation, scale, UV rect and tint come per instance
+        let instanced = program.attribute_location("InstanceTransform").is_some();
+        let instance_transform = self.fetch(pointer("InstanceTransform"), 0, instance);
+        let instance_scale = self.fetch(pointer("InstanceScale"), 0, instance);
+        let instance_rect = self.fetch(pointer("InstanceRect"), 0, instance);
+        let instance_tint = self.fetch(pointer("InstanceTint"), 0, instance);
+
+        indices.iter().map(|&index| {
+            let mut vertex = self.fetch(position, index, instance);
+            let mut uv = self.fetch(tex_coord, index, instance);
+            let mut color = self.fetch(color, index, instance);
+            if program.shading == Shading::VertexColor {
+                color.w = 1.0;
+            }
+
+            if instanced {
+                let (sin, cos) = instance_transform.w.sin_cos();
+                let (x, y) = (vertex.x * instance_scale.x, vertex.y * instance_scale.y);
+                vertex = vec4(cos * x - sin * y + instance_transform.x, sin * x + cos * y + instance_transform.y,
+                    vertex.z * instance_scale.z + instance_transform.z, 1.0);
+                uv = vec4(uv.x * instance_rect.z + instance_rect.x, uv.y * instance_rect.w + instance_rect.y, 0.0, 1.0);
+                color = color.component_mul(&instance_tint);
+            }
+
+            ShadedVertex {
+                position: transform * vec4(vertex.x, vertex.y, vertex.z, 1.0),
+                tex_coord: glm::vec2(uv.x, uv.y),
+                color,
+            }
+        }).collect()
+    }
+
+    fn shade_fragment(&self, program: &SoftProgram, tex_coord: Vec2, color: Vec4) -> Vec4 {
+        match program.shading {
+            Shading::VertexColor => color,
+            Shading::Textured => {
+                let offset = match program.uniform("Offset") {
+                    Some(UniformValue::Vec2(value)) => value.first().copied().unwrap_or_else(Vec2::zeros),
+                    _ => Vec2::zeros(),
+                };
+                let unit = match program.uniform("Texture") {
+                    Some(UniformValue::Sampler(units)) => units.first().map(|unit| unit.0 as usize).unwrap_or(0),
+                    _ => 0,
+                };
+
+                let texture = self.texture_units.get(unit).and_then(|texture| self.textures.get(texture));
+                let texel = match texture {
+                    Some(texture) => {
+                        let mut texel = texture.sample(tex_coord + offset);
+                        if texture.srgb {
+                            texel = vec4(srgb_to_linear(texel.x), srgb_to_linear(texel.y), srgb_to_linear(texel.z), texel.w);
+                        }
+                        texel
+                    },
+                    None => vec4(0., 0., 0., 1.),
+                };
+                texel.component_mul(&color)
+            }
+        }
+    }
+
+    fn rasterize(&mut self, program: &SoftProgram, triangle: [ShadedVertex; 3]) {
+        if triangle.iter().any(|vertex| vertex.position.w <= 0.0) {
+            return;
+        }
+
+        let (vx, vy, vw, vh) = self.viewport;
+        let screen: Vec<(f32, f32, f32)> = triangle.iter().map(|vertex| {
+            let inv_w = 1.0 / vertex.position.w;
+            let x = (vertex.position.x * inv_w + 1.0) * 0.5 * vw as f32 + vx as f32;
+            let y = (vertex.position.y * inv_w + 1.0) * 0.5 * vh as f32 + vy as f32;
+            (x, y, inv_w)
+        }).collect();
+
+        let edge = |a: (f32, f32, f32), b: (f32, f32, f32), x: f32, y: f32| (b.0 - a.0) * (y - a.1) - (b.1 - a.1) * (x - a.0);
+        let area = edge(screen[0], screen[1], screen[2].0, screen[2].1);
+        if area == 0.0 {
+            return;
+        }
+
+        // shading needs `&self` while the target is written, so fragments are shaded first
+        let (_, target_width, target_height) = self.target();
+        let min_x = screen.iter().map(|p| p.0).fold(f32::MAX, f32::min).floor().max(vx as f32).max(0.0) as usize;
+        let min_y = screen.iter().map(|p| p.1).fold(f32::MAX, f32::min).floor().max(vy as f32).max(0.0) as usize;
+        let max_x = (screen.iter().map(|p| p.0).fold(f32::MIN, f32::max).ceil() as i64)
+            .min((vx + vw) as i64).min(target_width as i64);
+        let max_y = (screen.iter().map(|p| p.1).fold(f32::MIN, f32::max).ceil() as i64)
+            .min((vy + vh) as i64).min(target_height as i64);
+
+        let mut fragments = vec![];
+        for y in min_y..max_y.max(0) as usize {
+            for x in min_x..max_x.max(0) as usize {
+                let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+                let w0 = edge(screen[1], screen[2], px, py) / area;
+                let w1 = edge(screen[2], screen[0], px, py) / area;
+                let w2 = edge(screen[0], screen[1], px, py) / area;
+                if w0 < 0.0 || w1 < 0.0 || w2 < 0.0 {
+                    continue;
+                }
+
+                // perspective-correct weights
+                let weights = [w0 * screen[0].2, w1 * screen[1].2, w2 * screen[2].2];
+                let sum: f32 = weights.iter().sum();
+                let tex_coord = (triangle[0].tex_coord * weights[0] + triangle[1].tex_coord * weights[1]
+                    + triangle[2].tex_coord * weights[2]) / sum;
+                let color = (triangle[0].color * weights[0] + triangle[1].color * weights[1]
+                    + triangle[2].color * weights[2]) / sum;
+
+                fragments.push((x, y, self.shade_fragment(program, tex_coord, color)));
+            }
+        }
+
+        let blend = self.blend;
+        let (target, target_width, _) = self.target();
+        for (x, y, source) in fragments {
+            let destination = &mut target[y * target_width + x];
+            *destination = match blend {
+                Some((src, dst)) => source * blend_factor(src, source) + destination.component_mul(&blend_factor_vec(dst, source)),
+                None => source,
+            };
+        }
+    }
+
+    fn draw_instance(&mut self, program: &SoftProgram, mode: gl::types::GLenum, indices: &[usize], instance: usize) {
+        let vertices = self.shade_vertices(program, indices, instance);
+
+        // points and lines aren't rasterized
+        let triangles: Vec<[usize; 3]> = match mode {
+            gl::TRIANGLES => (0..vertices.len() / 3).map(|i| [i * 3, i * 3 + 1, i * 3 + 2]).collect(),
+            gl::TRIANGLE_FAN => (1..vertices.len().saturating_sub(1)).map(|i| [0, i, i + 1]).collect(),
+            gl::TRIANGLE_STRIP => (0..vertices.len().saturating_sub(2))
+                .map(|i| if i % 2 == 0 { [i, i + 1, i + 2] } else { [i + 1, i, i + 2] })
+                .collect(),
+            _ => vec![],
+        };
+
+        for [a, b, c] in triangles {
+            self.rasterize(program, [vertices[a], vertices[b], vertices[c]]);
+        }
+    }
+}
+
+fn blend_factor(factor: gl::types::GLenum, source: Vec4) -> f32 {
+    match factor {
+        gl::ZERO => 0.0,
+        gl::SRC_ALPHA => source.w,
+        gl::ONE_MINUS_SRC_ALPHA => 1.0 - source.w,
+        _ => 1.0,
+    }
+}
+
+fn blend_factor_vec(factor: gl::types::GLenum, source: Vec4) -> Vec4 {
+    let factor = blend_factor(factor, source);
+    vec4(factor, factor, factor, factor)
+}
+
+fn to_rgba8(color: Vec4) -> [u8; 4] {
+    let channel = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+    [channel(color.x), channel(color.y), channel(color.z), channel(color.w)]
+}
+
+impl RenderBackend for SoftwareBackend {
+    fn release_queue(&self) -> ReleaseQueue {
+        self.release_queue.clone()
+    }
+
+    fn delete_shader(&mut self, shader: gl::types::GLuint) {
+        self.shaders.remove(&shader);
+    }
+
+    fn delete_program(&mut self, program: gl::types::GLuint) {
+        self.programs.remove(&program);
+    }
+
+    fn delete_buffer(&mut self, index: gl::types::GLuint) {
+        self.buffers.remove(&index);
+    }
+
+    fn delete_vertex_array(&mut self, vao: gl::types::GLuint) {
+        self.vertex_arrays.remove(&vao);
+    }
+
+    fn delete_texture(&mut self, texture: gl::types::GLuint) {
+        self.textures.remove(&texture);
+    }
+
+    fn delete_framebuffer(&mut self, framebuffer: gl::types::GLuint) {
+        self.framebuffers.remove(&framebuffer);
+    }
+
+    fn delete_renderbuffer(&mut self, _renderbuffer: gl::types::GLuint) {}
+
+    fn viewport(&mut self, x: i32, y: i32, width: i32, height: i32) {
+        self.viewport = (x, y, width, height);
+    }
+
+    fn clear_color(&mut self, r: f32, g: f32, b: f32, a: f32) {
+        self.clear_color = vec4(r, g, b, a);
+    }
+
+    fn clear(&mut self, mask: gl::types::GLbitfield) {
+        if mask & gl::COLOR_BUFFER_BIT != 0 {
+            let color = self.clear_color;
+            let (target, _, _) = self.target();
+            target.iter_mut().for_each(|pixel| *pixel = color);
+        }
+    }
+
+    fn enable_blend(&mut self, src: gl::types::GLenum, dst: gl::types::GLenum) {
+        self.blend = Some((src, dst));
+    }
+
+    fn new_buffer(&mut self, data: &[u8], _target: gl::types::GLenum, _usage: gl::types::GLenum) -> Result<gl::types::GLuint, RenderError> {
+        let index = self.gen_name();
+        self.buffers.insert(index, data.to_vec());
+        Ok(index)
+    }
+
+    fn buffer_data(&mut self, _target: gl::types::GLenum, index: gl::types::GLuint, data: &[u8], _usage: gl::types::GLenum) {
+        if let Some(buffer) = self.buffers.get_mut(&index) {
+            *buffer = data.to_vec();
+        }
+    }
+
+    fn bind_buffer(&mut self, target: gl::types::GLenum, index: gl::types::GLuint) {
+        if target == gl::ELEMENT_ARRAY_BUFFER {
+            self.element_buffer = index;
+        }
+    }
+
+    fn unbind_buffer(&mut self, target: gl::types::GLenum) {
+        self.bind_buffer(target, 0);
+    }
+
+    fn buffer_sub_data(&mut self, _target: gl::types::GLenum, index: gl::types::GLuint, offset: usize, data: &[u8]) {
+        if let Some(buffer) = self.buffers.get_mut(&index) {
+            if buffer.len() < offset + data.len() {
+                buffer.resize(offset + data.len(), 0);
+            }
+            buffer[offset..offset + data.len()].copy_from_slice(data);
+        }
+    }
+
+    fn bind_buffer_base(&mut self, _target: gl::types::GLenum, binding: gl::types::GLuint, index: gl::types::GLuint) {
+        self.uniform_bindings.insert(binding, index);
+    }
+
+    fn new_vertex_array(&mut self) -> Result<gl::types::GLuint, RenderError> {
+        let vao = self.gen_name();
+        self.vertex_arrays.insert(vao, HashMap::new());
+        Ok(vao)
+    }
+
+    fn bind_vertex_array(&mut self, vao: gl::types::GLuint) {
+        self.vertex_array = vao;
+    }
+
+    fn unbind_vertex_array(&mut self) {
+        self.vertex_array = 0;
+    }
+
+    fn vertex_attrib_pointer(&mut self, buffer_index: gl::types::GLuint, attribute: &VertexAttribute,
+        stride: gl::types::GLsizei, offset: usize) {
+        if let Some(attributes) = self.vertex_arrays.get_mut(&self.vertex_array) {
+            let pointer = attributes.entry(attribute.location).or_default();
+            *pointer = AttribPointer {
+                buffer: buffer_index,
+                size: attribute.count,
+                type_: attribute.type_,
+                normalized: attribute.normalized,
+                stride: stride as usize,
+                offset,
+                divisor: pointer.divisor,
+            };
+        }
+    }
+
+    fn vertex_attrib_divisor(&mut self, index: gl::types::GLuint, divisor: gl::types::GLuint) {
+        if let Some(attributes) = self.vertex_arrays.get_mut(&self.vertex_array) {
+            attributes.entry(index).or_default().divisor = divisor as usize;
+        }
+    }
+
+    fn gen_texture(&mut self) -> Result<gl::types::GLuint, RenderError> {
+        let texture = self.gen_name();
+        self.textures.insert(texture, SoftTexture::default());
+        Ok(texture)
+    }
+
+    fn bind_texture(&mut self, texture: gl::types::GLuint) {
+        self.texture_units[self.active_unit] = texture;
+    }
+
+    fn bind_texture_unit(&mut self, active_texture: gl::types::GLenum, texture: gl::types::GLuint) {
+        // like GL's GL_INVALID_ENUM, a value that names no unit leaves every binding alone
+        match TextureUnit::from_gl(active_texture).map(|unit| unit.0 as usize) {
+            Some(unit) if unit < self.texture_units.len() => self.active_unit = unit,
+            _ => return,
+        }
+        self.bind_texture(texture);
+    }
+
+    fn unbind_texture(&mut self) {
+        self.bind_texture(0);
+    }
+
+    fn set_texture_sampling(&mut self, descriptor: &TextureDescriptor) {
+        if let Some(texture) = self.bound_texture() {
+            texture.descriptor = *descriptor;
+        }
+    }
+
+    fn generate_mipmap(&mut self) {}
+
+    fn set_texture_swizzle(&mut self, swizzle: [gl::types::GLenum; 4]) {
+        if let Some(texture) = self.bound_texture() {
+            texture.swizzle = Some(swizzle);
+        }
+    }
+
+    fn set_pixel_store_mode(&mut self, mode: gl::types::GLenum, value: gl::types::GLint) {
+        if mode == gl::UNPACK_ALIGNMENT {
+            self.unpack_alignment = value.max(1) as usize;
+        }
+    }
+
+    fn set_texture_2d(&mut self, internal_format: gl::types::GLenum, mode: gl::types::GLenum, width: i32, height: i32, data_type: gl::types::GLenum, data: &[u8]) {
+        let (width, height) = (width as usize, height as usize);
+        let channels = match mode {
+            gl::RED => 1,
+            gl::RG => 2,
+            gl::RGB => 3,
+            _ => 4,
+        };
+        let channel_size = if data_type == gl::FLOAT { 4 } else { 1 };
+        let alignment = self.unpack_alignment;
+        let row = (width * channels * channel_size).next_multiple_of(alignment);
+
+        let mut texels = vec![vec4(0., 0., 0., 1.); width * height];
+        if !data.is_empty() {
+            for y in 0..height {
+                for x in 0..width {
+                    let mut texel = vec4(0., 0., 0., 1.);
+                    for channel in 0..channels {
+                        let offset = y * row + (x * channels + channel) * channel_size;
+                        texel[channel] = match (data_type, data.get(offset..offset + channel_size)) {
+                            (gl::FLOAT, Some(bytes)) => f32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+                            (_, Some(bytes)) => bytes[0] as f32 / 255.0,
+                            (_, None) => 0.0,
+                        };
+                    }
+                    texels[y * width + x] = texel;
+                }
+            }
+        }
+
+        if let Some(texture) = self.bound_texture() {
+            texture.width = width;
+            texture.height = height;
+            texture.texels = texels;
+            texture.srgb = internal_format == gl::SRGB8 || internal_format == gl::SRGB8_ALPHA8;
+        }
+    }
+
+    fn new_framebuffer(&mut self) -> Result<gl::types::GLuint, RenderError> {
+        let framebuffer = self.gen_name();
+        self.framebuffers.insert(framebuffer, 0);
+        Ok(framebuffer)
+    }
+
+    fn bind_framebuffer(&mut self, framebuffer: gl::types::GLuint) {
+        self.framebuffer = framebuffer;
+    }
+
+    fn framebuffer_texture(&mut self, attachment: gl::types::GLenum, texture: gl::types::GLuint) {
+        if attachment == gl::COLOR_ATTACHMENT0 {
+            if let Some(color) = self.framebuffers.get_mut(&self.framebuffer) {
+                *color = texture;
+            }
+        }
+    }
+
+    fn new_renderbuffer(&mut self, _internal_format: gl::types::GLenum, _width: i32, _height: i32) -> Result<gl::types::GLuint, RenderError> {
+        // there is no depth test, so depth/stencil storage is never needed
+        Ok(self.gen_name())
+    }
+
+    fn framebuffer_renderbuffer(&mut self, _attachment: gl::types::GLenum, _renderbuffer: gl::types::GLuint) {}
+
+    fn check_framebuffer_status(&mut self) -> gl::types::GLenum {
+        match self.framebuffers.get(&self.framebuffer) {
+            Some(color) if self.textures.contains_key(color) => gl::FRAMEBUFFER_COMPLETE,
+            _ => gl::FRAMEBUFFER_INCOMPLETE_MISSING_ATTACHMENT,
+        }
+    }
+
+    fn read_pixels(&mut self, x: i32, y: i32, width: i32, height: i32, data: &mut [u8]) {
+        let (target, target_width, target_height) = self.target();
+        for row in 0..height.max(0) as usize {
+            for column in 0..width.max(0) as usize {
+                // anything outside the target, including left of or below it, reads as transparent black
+                let source_x = usize::try_from(x as i64 + column as i64).ok().filter(|&source_x| source_x < target_width);
+                let source_y = usize::try_from(y as i64 + row as i64).ok().filter(|&source_y| source_y < target_height);
+                let rgba = match (source_x, source_y) {
+                    (Some(source_x), Some(source_y)) => to_rgba8(target[source_y * target_width + source_x]),
+                    _ => [0; 4],
+                };
+                let offset = (row * width as usize + column) * 4;
+                if let Some(pixel) = data.get_mut(offset..offset + 4) {
+                    pixel.copy_from_slice(&rgba);
+                }
+            }
+        }
+    }
+
+    fn shader_from_source(&mut self, _name: &str, source: &CStr, kind: gl::types::GLenum) -> Result<gl::types::GLuint, RenderError> {
+        let shader = self.gen_name();
+        self.shaders.insert(shader, (kind, source.to_string_lossy().into_owned()));
+        Ok(shader)
+    }
+
+    fn create_program(&mut self, _name: &str, shaders: [gl::types::GLuint; 2]) -> Result<gl::types::GLuint, RenderError> {
+        let program = self.gen_name();
+        let stages: Vec<(gl::types::GLenum, &str)> = shaders.iter()
+            .filter_map(|shader| self.shaders.get(shader))
+            .map(|(kind, source)| (*kind, source.as_str()))
+            .collect();
+        let reflection = glsl::reflect(&stages);
+        let shading = if reflection.uniforms.iter().any(|uniform| uniform.type_ == gl::SAMPLER_2D) {
+            Shading::Textured
+        } else {
+            Shading::VertexColor
+        };
+
+        self.programs.insert(program, SoftProgram {
+            reflection,
+            shading,
+            uniforms: HashMap::new(),
+            block_bindings: HashMap::new(),
+        });
+        Ok(program)
+    }
+
+    fn use_program(&mut self, program: gl::types::GLuint) {
+        self.current_program = program;
+    }
+
+    fn get_uniform_location(&mut self, program: gl::types::GLuint, name: &str) -> Result<gl::types::GLint, RenderError> {
+        self.programs.get(&program)
+            .and_then(|program| program.reflection.uniforms.iter().find(|uniform| uniform.name == name))
+            .map(|uniform| uniform.location)
+            .ok_or_else(|| RenderError::MissingUniform { program, name: name.to_string() })
+    }
+
+    fn active_uniforms(&mut self, program: gl::types::GLuint) -> Vec<ActiveVariable> {
+        self.programs.get(&program).map(|program| program.reflection.uniforms.clone()).unwrap_or_default()
+    }
+
+    fn active_attributes(&mut self, program: gl::types::GLuint) -> Vec<ActiveVariable> {
+        self.programs.get(&program).map(|program| program.reflection.attributes.clone()).unwrap_or_default()
+    }
+
+    fn active_uniform_blocks(&mut self, program: gl::types::GLuint) -> Vec<String> {
+        self.programs.get(&program).map(|program| program.reflection.uniform_blocks.clone()).unwrap_or_default()
+    }
+
+    fn uniform_block_binding(&mut self, program: gl::types::GLuint, name: &str, binding: gl::types::GLuint) -> Result<(), RenderError> {
+        match self.programs.get_mut(&program) {
+            Some(soft_program) if soft_program.reflection.uniform_blocks.iter().any(|block| block == name) => {
+                soft_program.block_bindings.insert(name.to_string(), binding);
+                Ok(())
+            },
+            _ => Err(RenderError::MissingUniform { program, name: name.to_string() }),
+        }
+    }
+
+    fn set_uniform(&mut self, location: gl::types::GLint, value: &UniformValue) {
+        if let Some(program) = self.programs.get_mut(&self.current_program) {
+            program.uniforms.insert(location, value.clone().into_owned());
+        }
+    }
+
+    fn draw_elements(&mut self, mode: gl::types::GLenum, count: i32, index_type: gl::types::GLenum, offset: usize) {
+        self.draw_elements_instanced(mode, count, index_type, offset, 1);
+    }
+
+    fn draw_elements_instanced(&mut self, mode: gl::types::GLenum, count: i32, index_type: gl::types::GLenum, offset: usize, instances: i32) {
+        let program = match self.programs.get(&self.current_program) {
+            Some(program) => program.clone(),
+            None => return,
+        };
+
+        let indices = self.indices(offset, count.max(0) as usize, index_type);
+        for instance in 0..instances.max(0) as usize {
+            self.draw_instance(&program, mode, &indices, instance);
+        }
+    }
+
+    fn draw_arrays(&mut self, mode: gl::types::GLenum, first: i32, count: i32) {
+        self.draw_arrays_instanced(mode, first, count, 1);
+    }
+
+    fn draw_arrays_instanced(&mut self, mode: gl::types::GLenum, first: i32, count: i32, instances: i32) {
+        let program = match self.programs.get(&self.current_program) {
+            Some(program) => program.clone(),
+            None => return,
+        };
+
+        let first = first.max(0) as usize;
+        let indices: Vec<usize> = (first..first + count.max(0) as usize).collect();
+        for instance in 0..instances.max(0) as usize {
+            self.draw_instance(&program, mode, &indices, instance);
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use specs::{Builder, RunNow, World, WorldExt};
+    use glm::{vec3, vec4};
+    use super::*;
+    use crate::common::deg2rad;
+    use crate::component::{Transform, Sprite, Spritesheet, AnimatedSprite, RenderLayer};
+    use crate::rendering::Preprocessor;
+    use crate::resource::{Camera, RenderDevice, ScreenScaling};
+    use crate::system::{InitSprite, InitAnimatedSprite, InitRender, Render};
+
+    const WIDTH: usize = 900;
+    const HEIGHT: usize = 700;
+
+    /// A world set up like `main`, drawing into a `SoftwareBackend` window.
+    fn world() -> World {
+        let mut world = World::new();
+        RunNow::setup(&mut InitSprite, &mut world);
+        RunNow::setup(&mut InitAnimatedSprite, &mut world);
+        RunNow::setup(&mut InitRender, &mut world);
+        RunNow::setup(&mut Render::default(), &mut world);
+
+        let (viewport, projection) = ScreenScaling::new(WIDTH as f32, HEIGHT as f32).apply(WIDTH as u32, HEIGHT as u32);
+        world.insert(viewport);
+        world.insert(projection);
+        world.insert(Camera(vec3(0., 0., 0.)));
+        world.insert(RenderDevice::new(SoftwareBackend::new(WIDTH, HEIGHT)));
+        world.insert(Preprocessor {
+            shader_dir: concat!(env!("CARGO_MANIFEST_DIR"), "/shaders").to_string(),
+            ..Default::default()
+        });
+        world
+    }
+
+    fn render(world: &World) -> Image {
+        InitSprite.run_now(world);
+        InitAnimatedSprite.run_now(world);
+        InitRender.run_now(world);
+        Render::default().run_now(world);
+
+        let device = world.read_resource::<RenderDevice>();
+        device.0.as_any().downcast_ref::<SoftwareBackend>().unwrap().window_image()
+    }
+
+    /// Compares `image` with `tests/reference/<name>`. Set `UPDATE_REFERENCE_IMAGES` to rewrite
+    /// the reference after an intended change; a mismatching image is saved to the temp dir.
+    fn assert_matches_reference(image: &Image, name: &str) {
+        let reference_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/reference").join(name);
+        if std::env::var_os("UPDATE_REFERENCE_IMAGES").is_some() {
+            image.save_png(&reference_path).unwrap();
+        }
+
+        let reference = Image::load(&reference_path).unwrap();
+        if !image.matches(&reference, 2) {
+            let actual_path = std::env::temp_dir().join(name);
+            image.save_png(&actual_path).unwrap();
+            panic!("{} differs from {} by up to {:?}", actual_path.display(), reference_path.display(),
+                image.max_difference(&reference));
+        }
+    }
+
+    #[test]
+    fn tower_sprite_matches_reference() {
+        let mut world = world();
+        world.create_entity()
+            .with(Transform {
+                position: vec3(100., 100., 0.),
+                rotation_rad: deg2rad(90.),
+                scale: vec3(0.5, 0.5, 0.5)
+            })
+            .with(Sprite {
+                image_name: "tower.png".to_string(),
+                rect: vec4(0., 0., 205., 198.),
+                ..Default::default()
+            })
+            .build();
+
+        assert_matches_reference(&render(&world), "tower.png");
+    }
+
+    #[test]
+    fn animated_tileset_sprite_matches_reference() {
+        let mut world = world();
+        let rects = vec![
+            vec4(32., 0., 224., 224.),
+            vec4(288., 0., 224., 224.),
+            vec4(32., 256., 224., 224.),
+        ];
+        world.create_entity()
+            .with(Transform {
+                position: vec3(300., 300., 0.),
+                rotation_rad: deg2rad(-90.),
+                scale: vec3(0.5, 0.5, 0.5)
+            })
+            .with(Spritesheet {
+                image_name: "tileset.png".to_string(),
+                rects: rects.clone(),
+                ..Default::default()
+            })
+            .with(AnimatedSprite {
+                rects: vec![rects.clone()],
+                rect_origin: rects[0],
+                current_anim: 0,
+                current_frame: 0,
+                frame_time: 0.5,
+                tick: 0.
+            })
+            .with(RenderLayer::new(1))
+            .build();
+
+        assert_matches_reference(&render(&world), "tileset.png");
+    }
+
+    #[test]
+    fn reads_outside_the_window_are_black() {
+        let mut backend = SoftwareBackend::new(2, 2);
+        backend.clear_color(1., 1., 1., 1.);
+        backend.clear(gl::COLOR_BUFFER_BIT);
+        let mut data = [0xAA; 16];
+
+        backend.read_pixels(-1, -1, 2, 2, &mut data);
+        assert_eq!(data, [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn binding_an_invalid_texture_unit_changes_nothing() {
+        let mut backend = SoftwareBackend::new(1, 1);
+        backend.bind_texture_unit(gl::TEXTURE2, 5);
+
+        backend.bind_texture_unit(0, 7);
+        backend.bind_texture_unit(gl::TEXTURE0 + 16, 7);
+
+        assert_eq!(backend.active_unit, 2);
+        assert_eq!(backend.texture_units[0], 0);
+        assert_eq!(backend.texture_units[2], 5);
+    }
+}
@@ -0,0 +1,21 @@
+use gl;
+
+/// How a material's colour is combined with what is already in the framebuffer.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum BlendMode {
+    #[default]
+    Alpha,
+    Additive,
+    Opaque,
+}
+
+impl BlendMode {
+    /// Source and destination factors for `enable_blend`.
+    pub fn factors(&self) -> (gl::types::GLenum, gl::types::GLenum) {
+        match self {
+            BlendMode::Alpha => (gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA),
+            BlendMode::Additive => (gl::SRC_ALPHA, gl::ONE),
+            BlendMode::Opaque => (gl::ONE, gl::ZERO),
+        }
+    }
+}
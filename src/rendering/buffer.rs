@@ -1,20 +1,138 @@
 use gl;
+use crate::rendering::{RenderBackend, RenderError};
+use crate::rendering::backend::{GpuObject, ReleaseQueue};
 
-pub fn new_buffer<T>(arr: &[T], target: gl::types::GLenum) -> Result<gl::types::GLuint, String> {
+/// How often a buffer's contents are expected to change, passed to GL as a hint.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BufferUsage {
+    /// Uploaded once, drawn many times.
+    #[default]
+    Static,
+    /// Updated now and then, drawn many times.
+    Dynamic,
+    /// Rewritten about every time it is drawn.
+    Stream,
+}
+
+impl BufferUsage {
+    pub fn gl_usage(&self) -> gl::types::GLenum {
+        match self {
+            BufferUsage::Static => gl::STATIC_DRAW,
+            BufferUsage::Dynamic => gl::DYNAMIC_DRAW,
+            BufferUsage::Stream => gl::STREAM_DRAW,
+        }
+    }
+}
+
+/// Owned GL buffer object, deleted once dropped. Not `Clone`; wrap it in an `Arc` to share it.
+#[derive(Default, Debug)]
+pub struct Buffer {
+    id: gl::types::GLuint,
+    target: gl::types::GLenum,
+    usage: BufferUsage,
+    size: usize,
+    release_queue: ReleaseQueue,
+}
+
+impl Buffer {
+    pub fn new(backend: &mut dyn RenderBackend, data: &[u8], target: gl::types::GLenum, usage: BufferUsage) -> Result<Buffer, RenderError> {
+        let id = backend.new_buffer(data, target, usage.gl_usage())?;
+
+        Ok(Buffer { id, target, usage, size: data.len(), release_queue: backend.release_queue() })
+    }
+
+    pub fn id(&self) -> gl::types::GLuint {
+        self.id
+    }
+
+    pub fn target(&self) -> gl::types::GLenum {
+        self.target
+    }
+
+    pub fn usage(&self) -> BufferUsage {
+        self.usage
+    }
+
+    /// Size of the storage in bytes.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Overwrites `data.len()` bytes at `offset`, which must lie within `size()`.
+    pub fn update(&self, backend: &mut dyn RenderBackend, offset: usize, data: &[u8]) {
+        backend.buffer_sub_data(self.target, self.id, offset, data);
+    }
+
+    /// Replaces the whole storage with `data`, resizing it if needed. The old storage is
+    /// orphaned, so GL doesn't wait for draws still reading it.
+    pub fn reallocate(&mut self, backend: &mut dyn RenderBackend, data: &[u8]) {
+        backend.buffer_data(self.target, self.id, data, self.usage.gl_usage());
+        self.size = data.len();
+    }
+}
+
+impl Drop for Buffer {
+    fn drop(&mut self) {
+        if self.id != 0 {
+            self.release_queue.release(GpuObject::Buffer(self.id));
+        }
+    }
+}
+
+/// Owned vertex array object, deleted once dropped.
+#[derive(Default, Debug)]
+pub struct VertexArray {
+    id: gl::types::GLuint,
+    release_queue: ReleaseQueue,
+}
+
+impl VertexArray {
+    pub fn new(backend: &mut dyn RenderBackend) -> Result<VertexArray, RenderError> {
+        let id = backend.new_vertex_array()?;
+
+        Ok(VertexArray { id, release_queue: backend.release_queue() })
+    }
+
+    pub fn id(&self) -> gl::types::GLuint {
+        self.id
+    }
+}
+
+impl Drop for VertexArray {
+    fn drop(&mut self) {
+        if self.id != 0 {
+            self.release_queue.release(GpuObject::VertexArray(self.id));
+        }
+    }
+}
+
+pub fn new_buffer<T>(arr: &[T], target: gl::types::GLenum, usage: gl::types::GLenum) -> Result<gl::types::GLuint, RenderError> {
     let mut index: gl::types::GLuint = 0;
     unsafe {
         gl::GenBuffers(1, &mut index);
+    }
+    buffer_data(target, index, arr, usage);
+
+    Ok(index)
+}
+
+pub fn buffer_data<T>(target: gl::types::GLenum, index: gl::types::GLuint, arr: &[T], usage: gl::types::GLenum) {
+    unsafe {
         gl::BindBuffer(target, index);
         gl::BufferData(
             target,
-            (arr.len() * ::std::mem::size_of::<T>()) as gl::types::GLsizeiptr,
+            std::mem::size_of_val(arr) as gl::types::GLsizeiptr,
             arr.as_ptr() as *const gl::types::GLvoid,
-            gl::STATIC_DRAW
+            usage
         );
         gl::BindBuffer(target, 0);
     }
+}
 
-    Ok(index)
+pub fn delete_buffer(index: gl::types::GLuint) {
+    unsafe {
+        gl::DeleteBuffers(1, &index);
+    }
 }
 
 pub fn bind_buffer(target: gl::types::GLenum, index: gl::types::GLuint) {
@@ -29,7 +147,26 @@ pub fn unbind_buffer(target: gl::types::GLenum) {
     }
 }
 
-pub fn new_vertex_array() -> Result<gl::types::GLuint, String> {
+pub fn buffer_sub_data<T>(target: gl::types::GLenum, index: gl::types::GLuint, offset: usize, arr: &[T]) {
+    unsafe {
+        gl::BindBuffer(target, index);
+        gl::BufferSubData(
+            target,
+            offset as gl::types::GLintptr,
+            std::mem::size_of_val(arr) as gl::types::GLsizeiptr,
+            arr.as_ptr() as *const gl::types::GLvoid
+        );
+        gl::BindBuffer(target, 0);
+    }
+}
+
+pub fn bind_buffer_base(target: gl::types::GLenum, binding: gl::types::GLuint, index: gl::types::GLuint) {
+    unsafe {
+        gl::BindBufferBase(target, binding, index);
+    }
+}
+
+pub fn new_vertex_array() -> Result<gl::types::GLuint, RenderError> {
     let mut vao: gl::types::GLuint = 0;
     unsafe {
         gl::GenVertexArrays(1, &mut vao);
@@ -38,6 +175,12 @@ pub fn new_vertex_array() -> Result<gl::types::GLuint, String> {
     Ok(vao)
 }
 
+pub fn delete_vertex_array(vao: gl::types::GLuint) {
+    unsafe {
+        gl::DeleteVertexArrays(1, &vao);
+    }
+}
+
 pub fn bind_vertex_array(vao: gl::types::GLuint) {
     unsafe {
         gl::BindVertexArray(vao);
@@ -51,7 +194,7 @@ pub fn unbind_vertex_array() {
 }
 
 pub fn vertex_attrib_pointer(buffer_index: gl::types::GLuint, index: gl::types::GLuint, size: gl::types::GLint,
-type_: gl::types::GLenum, normalized: gl::types::GLboolean, stride: gl::types::GLsizei) {
+type_: gl::types::GLenum, normalized: gl::types::GLboolean, stride: gl::types::GLsizei, offset: usize) {
     unsafe {
         gl::BindBuffer(gl::ARRAY_BUFFER, buffer_index);
         gl::EnableVertexAttribArray(index);
@@ -61,8 +204,21 @@ type_: gl::types::GLenum, normalized: gl::types::GLboolean, stride: gl::types::G
             type_,
             normalized,
             stride,
-            std::ptr::null()
+            offset as *const gl::types::GLvoid
         );
         gl::BindBuffer(gl::ARRAY_BUFFER, 0);
     }
+}
+
+pub fn vertex_attrib_divisor(index: gl::types::GLuint, divisor: gl::types::GLuint) {
+    unsafe {
+        gl::VertexAttribDivisor(index, divisor);
+    }
+}
+
+/// Views a slice of plain values as raw bytes for uploading through a `RenderBackend`.
+pub fn as_bytes<T: Copy>(arr: &[T]) -> &[u8] {
+    unsafe {
+        std::slice::from_raw_parts(arr.as_ptr() as *const u8, std::mem::size_of_val(arr))
+    }
 }
\ No newline at end of file
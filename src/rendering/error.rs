@@ -0,0 +1,158 @@
+use std::fmt;
+use std::io;
+use gl;
+
+/// One entry of a shader info log, with the position the driver reported if any.
+/// `file` is only set once the position has been mapped back through a preprocessed source.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShaderDiagnostic {
+    pub file: Option<String>,
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+    pub message: String,
+}
+
+#[derive(Debug)]
+pub enum RenderError {
+    Io { path: String, source: io::Error },
+    ImageDecode { path: String, message: String },
+    ImageEncode { path: String, message: String },
+    Preprocess { file: String, line: u32, message: String },
+    ShaderCompile { file: String, diagnostics: Vec<ShaderDiagnostic> },
+    ProgramLink { name: String, log: String },
+    MissingUniform { program: gl::types::GLuint, name: String },
+    UniformType { program: gl::types::GLuint, name: String, expected: gl::types::GLenum, found: gl::types::GLenum },
+    AtlasOverflow { name: String, width: usize, height: usize, max_size: usize },
+    FramebufferIncomplete { status: gl::types::GLenum },
+    VertexLayout { program: gl::types::GLuint, name: String, message: String },
+    IncompleteTexture { min_filter: gl::types::GLenum },
+    InvalidTextureUnit { active_texture: gl::types::GLenum },
+}
+
+impl RenderError {
+    pub fn shader_compile(file: &str, log: &str) -> RenderError {
+        RenderError::ShaderCompile {
+            file: file.to_string(),
+            diagnostics: parse_info_log(log),
+        }
+    }
+
+    pub fn program_link(name: &str, log: &str) -> RenderError {
+        RenderError::ProgramLink {
+            name: name.to_string(),
+            log: log.trim().to_string(),
+        }
+    }
+}
+
+impl fmt::Display for RenderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RenderError::Io { path, source } => write!(f, "{}: {}", path, source),
+            RenderError::ImageDecode { path, message } => write!(f, "{}: failed to decode image: {}", path, message),
+            RenderError::ImageEncode { path, message } => write!(f, "{}: failed to encode image: {}", path, message),
+            RenderError::Preprocess { file, line, message } => write!(f, "{}:{}: {}", file, line, message),
+            RenderError::ShaderCompile { file, diagnostics } => {
+                write!(f, "{}: shader failed to compile", file)?;
+                for diagnostic in diagnostics {
+                    write!(f, "\n{}", diagnostic.file.as_ref().unwrap_or(file))?;
+                    if let Some(line) = diagnostic.line {
+                        write!(f, ":{}", line)?;
+                    }
+                    if let Some(column) = diagnostic.column {
+                        write!(f, ":{}", column)?;
+                    }
+                    write!(f, ": {}", diagnostic.message)?;
+                }
+                Ok(())
+            },
+            RenderError::ProgramLink { name, log } => write!(f, "{}: program failed to link\n{}", name, log),
+            RenderError::MissingUniform { program, name } => write!(f, "uniform `{}` not found in program {}", name, program),
+            RenderError::UniformType { program, name, expected, found } =>
+                write!(f, "uniform `{}` of program {} has type {:#x}, tried to set {:#x}", name, program, expected, found),
+            RenderError::AtlasOverflow { name, width, height, max_size } =>
+                write!(f, "{} ({}x{}) doesn't fit into a {}x{} atlas page", name, width, height, max_size, max_size),
+            RenderError::FramebufferIncomplete { status } => write!(f, "framebuffer incomplete, status {:#x}", status),
+            RenderError::VertexLayout { program, name, message } =>
+                write!(f, "attribute `{}` of program {} doesn't match the vertex layout: {}", name, program, message),
+            RenderError::IncompleteTexture { min_filter } =>
+                write!(f, "min filter {:#x} samples mipmaps but the texture descriptor doesn't generate them", min_filter),
+            RenderError::InvalidTextureUnit { active_texture } =>
+                write!(f, "{:#x} is not a texture unit, expected gl::TEXTURE0 + n", active_texture),
+        }
+    }
+}
+
+impl std::error::Error for RenderError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RenderError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Splits a driver info log into diagnostics. Understands the common layouts:
+/// Mesa `0:12(5): error: ...`, NVIDIA `0(12) : error C0000: ...` and
+/// AMD/Intel `ERROR: 0:12: ...`.
+pub fn parse_info_log(log: &str) -> Vec<ShaderDiagnostic> {
+    log.lines()
+        .map(|line| line.trim_matches(|c: char| c.is_whitespace() || c == '\0'))
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let (prefix, text) = if let Some(text) = line.strip_prefix("ERROR: ") {
+                ("error: ", text)
+            } else if let Some(text) = line.strip_prefix("WARNING: ") {
+                ("warning: ", text)
+            } else {
+                ("", line)
+            };
+
+            match parse_location(text) {
+                Some((line, column, message)) => ShaderDiagnostic {
+                    file: None,
+                    line: Some(line),
+                    column,
+                    message: format!("{}{}", prefix, message),
+                },
+                None => ShaderDiagnostic {
+                    file: None,
+                    line: None,
+                    column: None,
+                    message: line.to_string(),
+                },
+            }
+        })
+        .collect()
+}
+
+fn parse_location(text: &str) -> Option<(u32, Option<u32>, &str)> {
+    // every layout starts with the source string index
+    let digits = text.find(|c: char| !c.is_ascii_digit())?;
+    if digits == 0 {
+        return None;
+    }
+    let rest = &text[digits..];
+
+    if let Some(rest) = rest.strip_prefix('(') {
+        let close = rest.find(')')?;
+        let line = rest[..close].parse().ok()?;
+        let message = rest[close + 1..].trim_start().strip_prefix(':')?;
+        return Some((line, None, message.trim()));
+    }
+
+    let rest = rest.strip_prefix(':')?;
+    let end = rest.find(|c: char| !c.is_ascii_digit())?;
+    let line = rest[..end].parse().ok()?;
+    let rest = &rest[end..];
+
+    if let Some(rest) = rest.strip_prefix('(') {
+        let close = rest.find(')')?;
+        let column = rest[..close].parse().ok();
+        let message = rest[close + 1..].trim_start().strip_prefix(':')?;
+        return Some((line, column, message.trim()));
+    }
+
+    let message = rest.strip_prefix(':')?;
+    Some((line, None, message.trim()))
+}
@@ -0,0 +1,148 @@
+use std::sync::Arc;
+use gl;
+use crate::rendering::{RenderBackend, RenderError, Texture, TextureDescriptor, TextureFormat, create_texture};
+use crate::rendering::backend::{GpuObject, ReleaseQueue};
+
+/// Offscreen framebuffer with a colour texture and an optional depth/stencil renderbuffer.
+/// The colour texture is an ordinary `Arc<Texture>`, so it can be put straight into a `Material`.
+#[derive(Default, Debug)]
+pub struct RenderTarget {
+    framebuffer: gl::types::GLuint,
+    depth_stencil: gl::types::GLuint,
+    pub color: Arc<Texture>,
+    pub width: usize,
+    pub height: usize,
+    release_queue: ReleaseQueue,
+}
+
+impl RenderTarget {
+    pub fn new(backend: &mut dyn RenderBackend, width: usize, height: usize, depth_stencil: bool,
+        descriptor: &TextureDescriptor) -> Result<RenderTarget, RenderError> {
+        let format = TextureFormat::from_u8(4, descriptor).unwrap_or_default();
+        let color = Arc::new(create_texture(backend, width, height, format, descriptor, &[])?);
+        backend.unbind_texture();
+
+        let mut target = RenderTarget {
+            framebuffer: backend.new_framebuffer()?,
+            depth_stencil: 0,
+            color,
+            width,
+            height,
+            release_queue: backend.release_queue(),
+        };
+
+        backend.bind_framebuffer(target.framebuffer);
+        backend.framebuffer_texture(gl::COLOR_ATTACHMENT0, target.color.index);
+        if depth_stencil {
+            target.depth_stencil = backend.new_renderbuffer(gl::DEPTH24_STENCIL8, width as i32, height as i32)?;
+            backend.framebuffer_renderbuffer(gl::DEPTH_STENCIL_ATTACHMENT, target.depth_stencil);
+        }
+
+        let status = backend.check_framebuffer_status();
+        backend.bind_framebuffer(0);
+        if status != gl::FRAMEBUFFER_COMPLETE {
+            return Err(RenderError::FramebufferIncomplete { status });
+        }
+
+        Ok(target)
+    }
+
+    pub fn id(&self) -> gl::types::GLuint {
+        self.framebuffer
+    }
+
+    pub fn has_depth_stencil(&self) -> bool {
+        self.depth_stencil != 0
+    }
+
+    /// Binds the framebuffer and sets the viewport to cover it.
+    pub fn bind(&self, backend: &mut dyn RenderBackend) {
+        backend.bind_framebuffer(self.framebuffer);
+        backend.viewport(0, 0, self.width as i32, self.height as i32);
+    }
+
+    /// Bits to pass to `clear` for every attachment of this target.
+    pub fn clear_mask(&self) -> gl::types::GLbitfield {
+        if self.has_depth_stencil() {
+            gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT | gl::STENCIL_BUFFER_BIT
+        } else {
+            gl::COLOR_BUFFER_BIT
+        }
+    }
+}
+
+impl Drop for RenderTarget {
+    fn drop(&mut self) {
+        if self.framebuffer != 0 {
+            self.release_queue.release(GpuObject::Framebuffer(self.framebuffer));
+        }
+        if self.depth_stencil != 0 {
+            self.release_queue.release(GpuObject::Renderbuffer(self.depth_stencil));
+        }
+    }
+}
+
+pub fn new_framebuffer() -> Result<gl::types::GLuint, RenderError> {
+    let mut framebuffer: gl::types::GLuint = 0;
+    unsafe {
+        gl::GenFramebuffers(1, &mut framebuffer);
+    }
+
+    Ok(framebuffer)
+}
+
+pub fn delete_framebuffer(framebuffer: gl::types::GLuint) {
+    unsafe {
+        gl::DeleteFramebuffers(1, &framebuffer);
+    }
+}
+
+pub fn bind_framebuffer(framebuffer: gl::types::GLuint) {
+    unsafe {
+        gl::BindFramebuffer(gl::FRAMEBUFFER, framebuffer);
+    }
+}
+
+pub fn framebuffer_texture(attachment: gl::types::GLenum, texture: gl::types::GLuint) {
+    unsafe {
+        gl::FramebufferTexture2D(gl::FRAMEBUFFER, attachment, gl::TEXTURE_2D, texture, 0);
+    }
+}
+
+pub fn new_renderbuffer(internal_format: gl::types::GLenum, width: i32, height: i32) -> Result<gl::types::GLuint, RenderError> {
+    let mut renderbuffer: gl::types::GLuint = 0;
+    unsafe {
+        gl::GenRenderbuffers(1, &mut renderbuffer);
+        gl::BindRenderbuffer(gl::RENDERBUFFER, renderbuffer);
+        gl::RenderbufferStorage(gl::RENDERBUFFER, internal_format, width, height);
+        gl::BindRenderbuffer(gl::RENDERBUFFER, 0);
+    }
+
+    Ok(renderbuffer)
+}
+
+pub fn delete_renderbuffer(renderbuffer: gl::types::GLuint) {
+    unsafe {
+        gl::DeleteRenderbuffers(1, &renderbuffer);
+    }
+}
+
+pub fn framebuffer_renderbuffer(attachment: gl::types::GLenum, renderbuffer: gl::types::GLuint) {
+    unsafe {
+        gl::FramebufferRenderbuffer(gl::FRAMEBUFFER, attachment, gl::RENDERBUFFER, renderbuffer);
+    }
+}
+
+pub fn read_pixels(x: i32, y: i32, width: i32, height: i32, data: &mut [u8]) {
+    debug_assert!(data.len() >= (width * height * 4) as usize);
+    unsafe {
+        // rows are tightly packed
+        gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+        gl::ReadPixels(x, y, width, height, gl::RGBA, gl::UNSIGNED_BYTE, data.as_mut_ptr() as *mut gl::types::GLvoid);
+        gl::PixelStorei(gl::PACK_ALIGNMENT, 4);
+    }
+}
+
+pub fn check_framebuffer_status() -> gl::types::GLenum {
+    unsafe { gl::CheckFramebufferStatus(gl::FRAMEBUFFER) }
+}
@@ -0,0 +1,98 @@
+use gl;
+use glm::{vec4, Vec3, Vec4};
+use crate::rendering::{RenderBackend, RenderError, Buffer, BufferUsage, VertexLayout, as_bytes};
+
+/// Attribute locations of the per-instance inputs declared in `shaders/instanced.vs`.
+pub const INSTANCE_TRANSFORM_LOCATION: gl::types::GLuint = 3;
+pub const INSTANCE_SCALE_LOCATION: gl::types::GLuint = 4;
+pub const INSTANCE_RECT_LOCATION: gl::types::GLuint = 5;
+pub const INSTANCE_TINT_LOCATION: gl::types::GLuint = 6;
+
+/// Everything that differs between two copies of an instanced mesh.
+#[derive(Debug, Clone, Copy)]
+pub struct InstanceData {
+    pub position: Vec3,
+    pub rotation_rad: f32,
+    pub scale: Vec3,
+    /// UV offset in `xy` and UV scale in `zw`, applied to the mesh UVs.
+    pub rect: Vec4,
+    pub tint: Vec4,
+}
+
+impl Default for InstanceData {
+    fn default() -> Self {
+        InstanceData {
+            position: Vec3::zeros(),
+            rotation_rad: 0.,
+            scale: glm::vec3(1., 1., 1.),
+            rect: vec4(0., 0., 1., 1.),
+            tint: vec4(1., 1., 1., 1.),
+        }
+    }
+}
+
+/// Interleaved per-instance inputs of `shaders/instanced.vs`, advanced once per instance.
+pub fn instance_layout() -> VertexLayout {
+    VertexLayout::new()
+        .with_attribute("InstanceTransform", INSTANCE_TRANSFORM_LOCATION, gl::FLOAT, 4, false)
+        .with_attribute("InstanceScale", INSTANCE_SCALE_LOCATION, gl::FLOAT, 3, false)
+        .with_attribute("InstanceRect", INSTANCE_RECT_LOCATION, gl::FLOAT, 4, false)
+        .with_attribute("InstanceTint", INSTANCE_TINT_LOCATION, gl::FLOAT, 4, false)
+        .with_divisor(1)
+}
+
+/// Per-instance vertex buffer for one vertex array. `upload` grows it to the next power of
+/// two when needed and re-attaches it to the vertex array with `instance_layout`.
+#[derive(Default)]
+pub struct InstanceBuffer {
+    capacity: usize,
+    vao: gl::types::GLuint,
+    buffer: Buffer,
+    staging: Vec<f32>,
+}
+
+impl InstanceBuffer {
+    pub fn new() -> InstanceBuffer {
+        InstanceBuffer::default()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn upload(&mut self, backend: &mut dyn RenderBackend, vao: gl::types::GLuint,
+        instances: &[InstanceData]) -> Result<(), RenderError> {
+        // a rebuilt vertex array needs the streams attached again
+        if instances.len() > self.capacity || vao != self.vao {
+            let capacity = instances.len().next_power_of_two().max(64).max(self.capacity);
+            self.reserve(backend, vao, capacity)?;
+        }
+
+        self.staging.clear();
+        for instance in instances {
+            self.staging.extend_from_slice(instance.position.as_slice());
+            self.staging.push(instance.rotation_rad);
+            self.staging.extend_from_slice(instance.scale.as_slice());
+            self.staging.extend_from_slice(instance.rect.as_slice());
+            self.staging.extend_from_slice(instance.tint.as_slice());
+        }
+
+        backend.buffer_sub_data(gl::ARRAY_BUFFER, self.buffer.id(), 0, as_bytes(self.staging.as_slice()));
+
+        Ok(())
+    }
+
+    fn reserve(&mut self, backend: &mut dyn RenderBackend, vao: gl::types::GLuint, capacity: usize) -> Result<(), RenderError> {
+        let layout = instance_layout();
+        self.buffer = Buffer::new(backend, &vec![0; capacity * layout.stride()], gl::ARRAY_BUFFER, BufferUsage::Stream)?;
+
+        backend.bind_vertex_array(vao);
+        layout.apply(backend, self.buffer.id());
+        backend.unbind_vertex_array();
+
+        self.capacity = capacity;
+        self.vao = vao;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,162 @@
+use std::ffi::CString;
+use gl;
+use crate::rendering::{RenderError, load_string};
+
+/// Where a line of preprocessed output came from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceLine {
+    pub file: String,
+    pub line: u32,
+}
+
+/// A shader stage ready to hand to the driver, with one `SourceLine` per output line.
+#[derive(Debug, Clone)]
+pub struct ShaderSource {
+    pub file: String,
+    pub code: CString,
+    pub lines: Vec<SourceLine>,
+}
+
+impl ShaderSource {
+    /// Maps a 1-based line of the generated code back to the file it was read from.
+    pub fn locate(&self, line: u32) -> Option<&SourceLine> {
+        self.lines.get(line.checked_sub(1)? as usize)
+    }
+
+    /// Rewrites the positions of a compile error so they point into the original files.
+    pub fn remap(&self, err: RenderError) -> RenderError {
+        match err {
+            RenderError::ShaderCompile { file, diagnostics } => RenderError::ShaderCompile {
+                file,
+                diagnostics: diagnostics.into_iter().map(|mut diagnostic| {
+                    if let Some(source_line) = diagnostic.line.and_then(|line| self.locate(line)) {
+                        diagnostic.file = Some(source_line.file.clone());
+                        diagnostic.line = Some(source_line.line);
+                    }
+                    diagnostic
+                }).collect(),
+            },
+            err => err,
+        }
+    }
+}
+
+/// Expands `#include "file"` (relative to `shader_dir`, each file at most once per stage),
+/// replaces the file's `#version` with `version` and injects the stage and caller defines.
+#[derive(Debug, Clone)]
+pub struct Preprocessor {
+    pub shader_dir: String,
+    pub version: String,
+    pub defines: Vec<(String, String)>,
+}
+
+impl Default for Preprocessor {
+    fn default() -> Self {
+        Preprocessor {
+            shader_dir: "shaders".to_string(),
+            version: "330 core".to_string(),
+            defines: vec![],
+        }
+    }
+}
+
+impl Preprocessor {
+    /// `#version` line for a context of the given GL version, e.g. `(4, 4, true)` → `440 core`.
+    pub fn with_gl_version(mut self, major: u32, minor: u32, core: bool) -> Self {
+        self.version = format!("{}{}0{}", major, minor, if core { " core" } else { "" });
+        self
+    }
+
+    pub fn define(mut self, name: &str, value: &str) -> Self {
+        self.defines.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Adds defines written as `NAME` or `NAME=VALUE`, as stored on a `Material`.
+    pub fn with_defines(mut self, defines: &[String]) -> Self {
+        for define in defines {
+            let (name, value) = match define.find('=') {
+                Some(split) => (&define[..split], &define[split + 1..]),
+                None => (&define[..], ""),
+            };
+            self = self.define(name, value);
+        }
+        self
+    }
+
+    pub fn process(&self, file: &str, kind: gl::types::GLenum) -> Result<ShaderSource, RenderError> {
+        let path = format!("{}/{}", self.shader_dir, file);
+        let mut output = String::new();
+        let mut lines = vec![];
+
+        let stage = match kind {
+            gl::VERTEX_SHADER => "VERTEX_SHADER",
+            gl::FRAGMENT_SHADER => "FRAGMENT_SHADER",
+            _ => "",
+        };
+
+        let mut header = vec![format!("#version {}", self.version)];
+        if !stage.is_empty() {
+            header.push(format!("#define {}", stage));
+        }
+        for (name, value) in &self.defines {
+            header.push(format!("#define {} {}", name, value));
+        }
+        for (index, line) in header.iter().enumerate() {
+            output.push_str(line);
+            output.push('\n');
+            lines.push(SourceLine { file: "<generated>".to_string(), line: index as u32 + 1 });
+        }
+
+        let mut included = vec![file.to_string()];
+        self.expand(&path, &mut output, &mut lines, &mut included)?;
+
+        let code = CString::new(output).map_err(|_| RenderError::Preprocess {
+            file: path.clone(),
+            line: 0,
+            message: "source contains nul".to_string(),
+        })?;
+
+        Ok(ShaderSource { file: path, code, lines })
+    }
+
+    fn expand(&self, path: &str, output: &mut String, lines: &mut Vec<SourceLine>, included: &mut Vec<String>) -> Result<(), RenderError> {
+        let text = load_string(path)?;
+
+        for (index, line) in text.lines().enumerate() {
+            let line_number = index as u32 + 1;
+            let directive = line.trim_start();
+
+            if directive.starts_with("#version") {
+                continue;
+            }
+
+            if let Some(rest) = directive.strip_prefix("#include") {
+                let include = parse_include(rest).ok_or_else(|| RenderError::Preprocess {
+                    file: path.to_string(),
+                    line: line_number,
+                    message: format!("malformed include `{}`", directive),
+                })?;
+
+                if !included.iter().any(|name| name == include) {
+                    included.push(include.to_string());
+                    self.expand(&format!("{}/{}", self.shader_dir, include), output, lines, included)?;
+                }
+                continue;
+            }
+
+            output.push_str(line);
+            output.push('\n');
+            lines.push(SourceLine { file: path.to_string(), line: line_number });
+        }
+
+        Ok(())
+    }
+}
+
+fn parse_include(rest: &str) -> Option<&str> {
+    let rest = rest.trim();
+    let name = rest.strip_prefix('"')?.strip_suffix('"')?;
+
+    if name.is_empty() { None } else { Some(name) }
+}
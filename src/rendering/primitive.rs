@@ -0,0 +1,65 @@
+use gl;
+
+/// How the vertices of a mesh, or its indices when it has any, are assembled into primitives.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Primitive {
+    Points,
+    Lines,
+    LineStrip,
+    LineLoop,
+    #[default]
+    Triangles,
+    TriangleStrip,
+    TriangleFan,
+}
+
+impl Primitive {
+    /// The `mode` for `draw_elements` and `draw_arrays`.
+    pub fn mode(&self) -> gl::types::GLenum {
+        match self {
+            Primitive::Points => gl::POINTS,
+            Primitive::Lines => gl::LINES,
+            Primitive::LineStrip => gl::LINE_STRIP,
+            Primitive::LineLoop => gl::LINE_LOOP,
+            Primitive::Triangles => gl::TRIANGLES,
+            Primitive::TriangleStrip => gl::TRIANGLE_STRIP,
+            Primitive::TriangleFan => gl::TRIANGLE_FAN,
+        }
+    }
+
+    /// The list primitive (points, lines or triangles) this one is made of.
+    pub fn list(&self) -> Primitive {
+        match self {
+            Primitive::Points => Primitive::Points,
+            Primitive::Lines | Primitive::LineStrip | Primitive::LineLoop => Primitive::Lines,
+            Primitive::Triangles | Primitive::TriangleStrip | Primitive::TriangleFan => Primitive::Triangles,
+        }
+    }
+
+    /// Rewrites `elements` as the equivalent `list()`, so meshes of different topologies can
+    /// be appended to one buffer and drawn together. Incomplete trailing primitives are dropped.
+    pub fn to_list(&self, elements: &[u32]) -> Vec<u32> {
+        let mut list = Vec::new();
+        self.append_list(elements, &mut list);
+        list
+    }
+
+    /// `to_list` appending to `list`, for reusing one buffer across meshes.
+    pub fn append_list(&self, elements: &[u32], list: &mut Vec<u32>) {
+        let n = elements.len();
+        match self {
+            Primitive::Points => list.extend_from_slice(elements),
+            Primitive::Lines => list.extend_from_slice(&elements[..n - n % 2]),
+            Primitive::Triangles => list.extend_from_slice(&elements[..n - n % 3]),
+            Primitive::LineStrip => list.extend(elements.windows(2).flatten()),
+            Primitive::LineLoop if n < 2 => {},
+            Primitive::LineLoop => list.extend(elements.windows(2).flatten().copied()
+                .chain([elements[n - 1], elements[0]])),
+            // every other strip triangle is flipped to keep the winding consistent
+            Primitive::TriangleStrip => list.extend(elements.windows(3).enumerate()
+                .flat_map(|(i, t)| if i % 2 == 0 { [t[0], t[1], t[2]] } else { [t[1], t[0], t[2]] })),
+            Primitive::TriangleFan => list.extend((1..n.saturating_sub(1))
+                .flat_map(|i| [elements[0], elements[i], elements[i + 1]])),
+        }
+    }
+}
@@ -2,16 +2,35 @@ use std::ffi::CString;
 use std::fs::File;
 use std::path::{Path, PathBuf};
 use std::io::{Error, ErrorKind, Read};
+use crate::rendering::RenderError;
+
+pub fn load_cstring(file_path: &str) -> Result<CString, RenderError> {
+    read_cstring(file_path).map_err(|source| RenderError::Io { path: file_path.to_string(), source })
+}
+
+pub fn load_string(file_path: &str) -> Result<String, RenderError> {
+    read_cstring(file_path)
+        .and_then(|buffer| buffer.into_string().map_err(|_| Error::new(ErrorKind::InvalidData, "Data is not UTF-8!")))
+        .map_err(|source| RenderError::Io { path: file_path.to_string(), source })
+}
+
+/// Resolves a `/`-separated asset location against the executable's directory. Absolute
+/// locations, as used by tests that don't run next to the assets, are returned as-is.
+pub fn resolve_path(location: &str) -> Result<PathBuf, Error> {
+    if Path::new(location).is_absolute() {
+        return Ok(PathBuf::from(location));
+    }
 
-pub fn load_cstring(file_path: &str) -> Result<CString, Error> {
     let exe_file_name = std::env::current_exe()?;
 
     let exe_path = exe_file_name.parent().ok_or(Error::new(ErrorKind::NotFound, "Path not found"))?;
 
-    println!("{:?}", exe_path);
-    let mut file = File::open(
-        str_location_to_path(exe_path, file_path)
-    )?;
+    Ok(str_location_to_path(exe_path, location))
+}
+
+fn read_cstring(file_path: &str) -> Result<CString, Error> {
+    let path = resolve_path(file_path)?;
+    let mut file = File::open(path)?;
 
     // allocate buffer of the same size as file
     let mut buffer: Vec<u8> = Vec::with_capacity(
@@ -0,0 +1,97 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use gl;
+use png;
+use stb_image::image::{load_with_depth, LoadResult};
+use crate::rendering::{RenderBackend, RenderError, RenderTarget};
+
+/// RGBA8 pixels, top row first.
+#[derive(Default, Debug, Clone)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
+}
+
+impl Image {
+    pub fn save_png<P: AsRef<Path>>(&self, path: P) -> Result<(), RenderError> {
+        let path = path.as_ref();
+        let io_error = |source| RenderError::Io { path: path.display().to_string(), source };
+        let encode_error = |err: png::EncodingError| RenderError::ImageEncode {
+            path: path.display().to_string(),
+            message: err.to_string(),
+        };
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(io_error)?;
+        }
+        let file = File::create(path).map_err(io_error)?;
+
+        let mut encoder = png::Encoder::new(BufWriter::new(file), self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::RGBA);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().map_err(encode_error)?;
+        writer.write_image_data(&self.pixels).map_err(encode_error)
+    }
+
+    /// Decodes an image file as RGBA8, e.g. a reference image for `matches`.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Image, RenderError> {
+        let path = path.as_ref().display().to_string();
+        match load_with_depth(&path, 4, false) {
+            LoadResult::ImageU8(img) => Ok(Image { width: img.width, height: img.height, pixels: img.data }),
+            LoadResult::ImageF32(_) => Err(RenderError::ImageDecode { path, message: "expected an 8-bit image".to_string() }),
+            LoadResult::Error(message) => Err(RenderError::ImageDecode { path, message }),
+        }
+    }
+
+    /// Largest per-channel difference to `other`, `None` if the sizes differ.
+    pub fn max_difference(&self, other: &Image) -> Option<u8> {
+        if (self.width, self.height) != (other.width, other.height) || self.pixels.len() != other.pixels.len() {
+            return None;
+        }
+
+        Some(self.pixels.iter().zip(&other.pixels).map(|(a, b)| a.max(b) - a.min(b)).max().unwrap_or(0))
+    }
+
+    /// True if no channel differs from `reference` by more than `tolerance`.
+    pub fn matches(&self, reference: &Image, tolerance: u8) -> bool {
+        self.max_difference(reference).map(|difference| difference <= tolerance).unwrap_or(false)
+    }
+}
+
+/// Reads a rectangle of `framebuffer` (0 for the window). GL returns the bottom row first,
+/// so rows are flipped unless `flip_rows` is false because the content was drawn upside down.
+pub fn read_pixels(backend: &mut dyn RenderBackend, framebuffer: gl::types::GLuint, x: i32, y: i32,
+    width: usize, height: usize, flip_rows: bool) -> Image {
+    let mut pixels = vec![0; width * height * 4];
+
+    backend.bind_framebuffer(framebuffer);
+    backend.read_pixels(x, y, width as i32, height as i32, &mut pixels);
+    backend.bind_framebuffer(0);
+
+    if flip_rows {
+        let row = width * 4;
+        for top in 0..height / 2 {
+            let bottom = height - 1 - top;
+            let (upper, lower) = pixels.split_at_mut(bottom * row);
+            upper[top * row..(top + 1) * row].swap_with_slice(&mut lower[..row]);
+        }
+    }
+
+    Image { width, height, pixels }
+}
+
+/// Contents of a render target. `OffscreenCamera` targets are drawn with a flipped projection
+/// and are already top row first, pass false for them; the post-process scene target is drawn
+/// like the window and needs `flip_rows`.
+pub fn read_render_target(backend: &mut dyn RenderBackend, target: &RenderTarget, flip_rows: bool) -> Image {
+    read_pixels(backend, target.id(), 0, 0, target.width, target.height, flip_rows)
+}
+
+/// `dir/screenshot-<milliseconds since the epoch>.png`
+pub fn screenshot_path<P: AsRef<Path>>(dir: P) -> PathBuf {
+    let millis = SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_millis()).unwrap_or(0);
+    dir.as_ref().join(format!("screenshot-{}.png", millis))
+}
@@ -1,8 +1,153 @@
 use std::{ffi::{CString, CStr}};
-use glm::{Vec2, Vec3, value_ptr};
+use std::collections::HashMap;
 use gl;
+use crate::rendering::{RenderBackend, RenderError, Preprocessor, ShaderSource, Uniform, FRAME_DATA_BLOCK, FRAME_DATA_BINDING};
+use crate::rendering::backend::{GpuObject, ReleaseQueue};
 
-pub fn create_program(shaders: [gl::types::GLuint; 2]) -> Result<gl::types::GLuint, String> {
+/// Owned compiled shader stage. Only needed until the program is linked; dropping it
+/// afterwards deletes the shader object.
+#[derive(Default, Debug)]
+pub struct Shader {
+    id: gl::types::GLuint,
+    kind: gl::types::GLenum,
+    release_queue: ReleaseQueue,
+}
+
+impl Shader {
+    pub fn compile(backend: &mut dyn RenderBackend, source: &ShaderSource, kind: gl::types::GLenum) -> Result<Shader, RenderError> {
+        let id = backend.shader_from_source(&source.file, &source.code, kind)
+            .map_err(|err| source.remap(err))?;
+
+        Ok(Shader { id, kind, release_queue: backend.release_queue() })
+    }
+
+    pub fn id(&self) -> gl::types::GLuint {
+        self.id
+    }
+
+    pub fn kind(&self) -> gl::types::GLenum {
+        self.kind
+    }
+}
+
+impl Drop for Shader {
+    fn drop(&mut self) {
+        if self.id != 0 {
+            self.release_queue.release(GpuObject::Shader(self.id));
+        }
+    }
+}
+
+/// An active uniform or vertex attribute of a linked program.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActiveVariable {
+    pub name: String,
+    pub location: gl::types::GLint,
+    pub type_: gl::types::GLenum,
+    pub size: gl::types::GLint,
+}
+
+/// Owned linked program, deleted once dropped. Not `Clone`; wrap it in an `Arc` to share
+/// one program between several materials.
+///
+/// Active uniforms and attributes are queried once at link time, so the setters below
+/// neither allocate nor round-trip to the driver.
+#[derive(Default, Debug)]
+pub struct Program {
+    id: gl::types::GLuint,
+    uniforms: HashMap<String, ActiveVariable>,
+    attributes: HashMap<String, ActiveVariable>,
+    uniform_blocks: Vec<String>,
+    release_queue: ReleaseQueue,
+}
+
+impl Program {
+    pub fn link(backend: &mut dyn RenderBackend, name: &str, shaders: [&Shader; 2]) -> Result<Program, RenderError> {
+        let id = backend.create_program(name, [shaders[0].id(), shaders[1].id()])?;
+
+        let uniforms = backend.active_uniforms(id).into_iter()
+            .map(|uniform| (uniform.name.clone(), uniform))
+            .collect();
+        let attributes = backend.active_attributes(id).into_iter()
+            .map(|attribute| (attribute.name.clone(), attribute))
+            .collect();
+
+        let uniform_blocks = backend.active_uniform_blocks(id);
+
+        Ok(Program { id, uniforms, attributes, uniform_blocks, release_queue: backend.release_queue() })
+    }
+
+    pub fn id(&self) -> gl::types::GLuint {
+        self.id
+    }
+
+    pub fn uniform(&self, name: &str) -> Option<&ActiveVariable> {
+        self.uniforms.get(name)
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&ActiveVariable> {
+        self.attributes.get(name)
+    }
+
+    pub fn has_uniform_block(&self, name: &str) -> bool {
+        self.uniform_blocks.iter().any(|block| block == name)
+    }
+
+    pub fn bind_uniform_block(&self, backend: &mut dyn RenderBackend, name: &str, binding: gl::types::GLuint) -> Result<(), RenderError> {
+        backend.uniform_block_binding(self.id, name, binding)
+    }
+
+    /// Location of `name`, checked against the type the program declares for it.
+    pub fn uniform_location(&self, name: &str, type_: gl::types::GLenum) -> Result<gl::types::GLint, RenderError> {
+        let uniform = self.uniform(name)
+            .ok_or_else(|| RenderError::MissingUniform { program: self.id, name: name.to_string() })?;
+
+        if uniform.type_ != type_ {
+            return Err(RenderError::UniformType {
+                program: self.id,
+                name: name.to_string(),
+                expected: uniform.type_,
+                found: type_,
+            });
+        }
+
+        Ok(uniform.location)
+    }
+
+    /// Writes `value` to `name`. The program has to be in use.
+    pub fn set_uniform<U: Uniform + ?Sized>(&self, backend: &mut dyn RenderBackend, name: &str, value: &U) -> Result<(), RenderError> {
+        let location = self.uniform_location(name, value.gl_type())?;
+        backend.set_uniform(location, &value.value());
+
+        Ok(())
+    }
+}
+
+impl Drop for Program {
+    fn drop(&mut self) {
+        if self.id != 0 {
+            self.release_queue.release(GpuObject::Program(self.id));
+        }
+    }
+}
+
+/// Preprocesses and compiles `<name>.vs` and `<name>.fs` and links them into a program.
+pub fn load_program(backend: &mut dyn RenderBackend, preprocessor: &Preprocessor, name: &str) -> Result<Program, RenderError> {
+    let vertex_shader_src = preprocessor.process(&format!("{}.vs", name), gl::VERTEX_SHADER)?;
+    let vertex_shader = Shader::compile(backend, &vertex_shader_src, gl::VERTEX_SHADER)?;
+
+    let fragment_shader_src = preprocessor.process(&format!("{}.fs", name), gl::FRAGMENT_SHADER)?;
+    let fragment_shader = Shader::compile(backend, &fragment_shader_src, gl::FRAGMENT_SHADER)?;
+
+    let program = Program::link(backend, name, [&vertex_shader, &fragment_shader])?;
+    if program.has_uniform_block(FRAME_DATA_BLOCK) {
+        program.bind_uniform_block(backend, FRAME_DATA_BLOCK, FRAME_DATA_BINDING)?;
+    }
+
+    Ok(program)
+}
+
+pub fn create_program(name: &str, shaders: [gl::types::GLuint; 2]) -> Result<gl::types::GLuint, RenderError> {
     let program_id = unsafe { gl::CreateProgram() };
 
     for shader in shaders {
@@ -34,7 +179,12 @@ pub fn create_program(shaders: [gl::types::GLuint; 2]) -> Result<gl::types::GLui
             );
         }
 
-        return Err(error.to_string_lossy().into_owned());
+        for shader in shaders {
+            unsafe { gl::DetachShader(program_id, shader) };
+        }
+        delete_program(program_id);
+
+        return Err(RenderError::program_link(name, &error.to_string_lossy()));
     }
 
     for shader in shaders {
@@ -44,23 +194,39 @@ pub fn create_program(shaders: [gl::types::GLuint; 2]) -> Result<gl::types::GLui
     Ok(program_id)
 }
 
+pub fn delete_program(program: gl::types::GLuint) {
+    unsafe {
+        gl::DeleteProgram(program);
+    }
+}
+
+pub fn delete_shader(shader: gl::types::GLuint) {
+    unsafe {
+        gl::DeleteShader(shader);
+    }
+}
+
 pub fn use_program(program: gl::types::GLuint) {
     unsafe {
         gl::UseProgram(program);
     }
 }
 
-pub fn get_uniform_location(program: gl::types::GLuint, name: &str) -> Result<gl::types::GLint, String> {
+pub fn get_uniform_location(program: gl::types::GLuint, name: &str) -> Result<gl::types::GLint, RenderError> {
     let c_name = CString::new(name).unwrap();
     let uniform_location: gl::types::GLint;
     unsafe {
         uniform_location = gl::GetUniformLocation(program, c_name.as_ptr() as *const gl::types::GLchar);
     }
 
+    if uniform_location == -1 {
+        return Err(RenderError::MissingUniform { program, name: name.to_string() });
+    }
+
     Ok(uniform_location)
 }
 
-pub fn get_attrib_location(program: gl::types::GLuint, name: &str) -> Result<gl::types::GLint, String> {
+pub fn get_attrib_location(program: gl::types::GLuint, name: &str) -> Result<gl::types::GLint, RenderError> {
     let c_name = CString::new(name).unwrap();
     let attrib_location: gl::types::GLint;
     unsafe {
@@ -70,7 +236,95 @@ pub fn get_attrib_location(program: gl::types::GLuint, name: &str) -> Result<gl:
     Ok(attrib_location)
 }
 
-pub fn shader_from_source(source: &CStr, kind: gl::types::GLuint) -> Result<gl::types::GLuint, String> {
+pub fn get_active_uniforms(program: gl::types::GLuint) -> Vec<ActiveVariable> {
+    get_active_variables(program, gl::ACTIVE_UNIFORMS, gl::ACTIVE_UNIFORM_MAX_LENGTH, |index, max_len, len, size, type_, name| unsafe {
+        gl::GetActiveUniform(program, index, max_len, len, size, type_, name);
+    }, |name| unsafe { gl::GetUniformLocation(program, name) })
+}
+
+pub fn get_active_attributes(program: gl::types::GLuint) -> Vec<ActiveVariable> {
+    get_active_variables(program, gl::ACTIVE_ATTRIBUTES, gl::ACTIVE_ATTRIBUTE_MAX_LENGTH, |index, max_len, len, size, type_, name| unsafe {
+        gl::GetActiveAttrib(program, index, max_len, len, size, type_, name);
+    }, |name| unsafe { gl::GetAttribLocation(program, name) })
+}
+
+fn get_active_variables<Q, L>(program: gl::types::GLuint, count_param: gl::types::GLenum, max_len_param: gl::types::GLenum,
+    query: Q, location_of: L) -> Vec<ActiveVariable>
+where
+    Q: Fn(gl::types::GLuint, gl::types::GLsizei, *mut gl::types::GLsizei, *mut gl::types::GLint, *mut gl::types::GLenum, *mut gl::types::GLchar),
+    L: Fn(*const gl::types::GLchar) -> gl::types::GLint
+{
+    let mut count: gl::types::GLint = 0;
+    let mut max_len: gl::types::GLint = 0;
+    unsafe {
+        gl::GetProgramiv(program, count_param, &mut count);
+        gl::GetProgramiv(program, max_len_param, &mut max_len);
+    }
+
+    let mut variables = vec![];
+    for index in 0..count as gl::types::GLuint {
+        let mut buffer: Vec<u8> = vec![0; max_len.max(1) as usize];
+        let mut len: gl::types::GLsizei = 0;
+        let mut size: gl::types::GLint = 0;
+        let mut type_: gl::types::GLenum = 0;
+        query(index, max_len, &mut len, &mut size, &mut type_, buffer.as_mut_ptr() as *mut gl::types::GLchar);
+        buffer.truncate(len as usize);
+
+        let c_name = CString::new(buffer).unwrap();
+        let location = location_of(c_name.as_ptr());
+        // uniform block members and built-ins have no location of their own
+        if location == -1 {
+            continue;
+        }
+
+        let name = c_name.to_string_lossy();
+        variables.push(ActiveVariable {
+            name: name.trim_end_matches("[0]").to_string(),
+            location,
+            type_,
+            size,
+        });
+    }
+
+    variables
+}
+
+pub fn get_active_uniform_blocks(program: gl::types::GLuint) -> Vec<String> {
+    let mut count: gl::types::GLint = 0;
+    let mut max_len: gl::types::GLint = 0;
+    unsafe {
+        gl::GetProgramiv(program, gl::ACTIVE_UNIFORM_BLOCKS, &mut count);
+        gl::GetProgramiv(program, gl::ACTIVE_UNIFORM_BLOCK_MAX_NAME_LENGTH, &mut max_len);
+    }
+
+    (0..count as gl::types::GLuint).map(|index| {
+        let mut buffer: Vec<u8> = vec![0; max_len.max(1) as usize];
+        let mut len: gl::types::GLsizei = 0;
+        unsafe {
+            gl::GetActiveUniformBlockName(program, index, max_len, &mut len, buffer.as_mut_ptr() as *mut gl::types::GLchar);
+        }
+        buffer.truncate(len as usize);
+
+        String::from_utf8_lossy(&buffer).into_owned()
+    }).collect()
+}
+
+pub fn uniform_block_binding(program: gl::types::GLuint, name: &str, binding: gl::types::GLuint) -> Result<(), RenderError> {
+    let c_name = CString::new(name).unwrap();
+    let block_index = unsafe { gl::GetUniformBlockIndex(program, c_name.as_ptr() as *const gl::types::GLchar) };
+
+    if block_index == gl::INVALID_INDEX {
+        return Err(RenderError::MissingUniform { program, name: name.to_string() });
+    }
+
+    unsafe {
+        gl::UniformBlockBinding(program, block_index, binding);
+    }
+
+    Ok(())
+}
+
+pub fn shader_from_source(name: &str, source: &CStr, kind: gl::types::GLuint) -> Result<gl::types::GLuint, RenderError> {
    let id = unsafe { gl::CreateShader(kind) };
 
     unsafe {
@@ -95,7 +349,9 @@ pub fn shader_from_source(source: &CStr, kind: gl::types::GLuint) -> Result<gl::
             gl::GetShaderInfoLog(id, len, std::ptr::null_mut(), error.as_ptr() as *mut gl::types::GLchar);
         }
 
-        return Err(error.to_string_lossy().into_owned());
+        delete_shader(id);
+
+        return Err(RenderError::shader_compile(name, &error.to_string_lossy()));
     }
 
     Ok(id)
@@ -103,25 +359,11 @@ pub fn shader_from_source(source: &CStr, kind: gl::types::GLuint) -> Result<gl::
 
 fn create_whitespace_cstring_with_len(len: usize) -> CString {
     // allocate buffer to correct size
-    let mut buffer: Vec<u8> = Vec::with_capacity(len as usize + 1);
+    let mut buffer: Vec<u8> = Vec::with_capacity(len + 1);
     // fill it with len spaces
-    buffer.extend([b' '].iter().cycle().take(len as usize));
+    buffer.extend([b' '].iter().cycle().take(len));
     // convert buffer to CString
     unsafe {
         CString::from_vec_unchecked(buffer)
     }
 }
-
-pub fn push_uniform_vec2(val: &Vec2, program: gl::types::GLuint, uniform_name: &str) {
-    let uniform_location = get_uniform_location(program, uniform_name).unwrap();
-    unsafe {
-        gl::Uniform2fv(uniform_location, 1, value_ptr(val).as_ptr());
-    }
-}
-
-pub fn push_uniform_vec3(val: &Vec3, program: gl::types::GLuint, uniform_name: &str) {
-    let uniform_location = get_uniform_location(program, uniform_name).unwrap();
-    unsafe {
-        gl::Uniform3fv(uniform_location, 1, value_ptr(val).as_ptr());
-    }
-}
\ No newline at end of file
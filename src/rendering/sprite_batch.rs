@@ -0,0 +1,217 @@
+use std::cmp::Ordering;
+use std::sync::Arc;
+use gl;
+use glm::{Vec2, Vec3, Vec4};
+use crate::rendering::{RenderBackend, RenderError, Program, Buffer, BufferUsage, VertexArray, VertexLayout, BlendMode, Primitive, as_bytes};
+
+/// State shared by every sprite in one draw call.
+#[derive(Debug, Clone)]
+pub struct BatchKey {
+    pub program: Arc<Program>,
+    pub texture: gl::types::GLuint,
+    pub blend: BlendMode,
+    /// One of the list primitives, see `Primitive::to_list`.
+    pub primitive: Primitive,
+}
+
+impl BatchKey {
+    fn sort_key(&self) -> (gl::types::GLuint, gl::types::GLuint, BlendMode, Primitive) {
+        (self.program.id(), self.texture, self.blend, self.primitive)
+    }
+}
+
+/// Back-to-front position of a sprite; smaller orders are drawn first.
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+pub struct DrawOrder {
+    pub layer: i32,
+    pub z: f32,
+    /// Tie-breaker within a layer and depth, e.g. the negated y for top-down scenes.
+    pub y: f32,
+}
+
+impl DrawOrder {
+    fn compare(&self, other: &DrawOrder) -> Ordering {
+        self.layer.cmp(&other.layer)
+            .then_with(|| self.z.total_cmp(&other.z))
+            .then_with(|| self.y.total_cmp(&other.y))
+    }
+}
+
+/// A vertex already in world space, so sprites with different transforms can share a draw.
+#[derive(Debug, Clone, Copy)]
+pub struct BatchVertex {
+    pub position: Vec3,
+    pub uv: Vec2,
+    pub color: Vec4,
+}
+
+struct BatchItem {
+    key: BatchKey,
+    order: DrawOrder,
+    first_vertex: usize,
+    vertex_count: usize,
+    first_index: usize,
+    index_count: usize,
+}
+
+struct Batch {
+    key: BatchKey,
+    first_index: usize,
+    index_count: usize,
+}
+
+/// Collects sprites for a frame, sorts them back to front by `DrawOrder`, and sprites with equal
+/// orders by program, texture, blend mode and primitive, then streams them into one interleaved
+/// vertex buffer (`VertexLayout::sprite`) and one index buffer, drawing every run of equal state
+/// in one call. There is no depth buffer, so the order alone decides what ends up on top.
+/// The buffers only grow, to the next power of two, so steady scenes never reallocate.
+#[derive(Default)]
+pub struct SpriteBatch {
+    vertices: Vec<BatchVertex>,
+    indices: Vec<u32>,
+    items: Vec<BatchItem>,
+
+    interleaved: Vec<f32>,
+    sorted_indices: Vec<u32>,
+
+    capacity: usize,
+    index_capacity: usize,
+    vbo: Buffer,
+    ibo: Buffer,
+    vao: VertexArray,
+}
+
+impl SpriteBatch {
+    pub fn new() -> SpriteBatch {
+        SpriteBatch::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Queues a sprite. `indices` form a list of `key.primitive` relative to `vertices`.
+    pub fn push<V, I>(&mut self, key: BatchKey, order: DrawOrder, vertices: V, indices: I)
+    where V: IntoIterator<Item = BatchVertex>, I: IntoIterator<Item = u32> {
+        let (first_vertex, first_index) = (self.vertices.len(), self.indices.len());
+        self.vertices.extend(vertices);
+        self.indices.extend(indices);
+
+        self.items.push(BatchItem {
+            key,
+            order,
+            first_vertex,
+            vertex_count: self.vertices.len() - first_vertex,
+            first_index,
+            index_count: self.indices.len() - first_index,
+        });
+    }
+
+    /// Drops everything queued since the last flush.
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+        self.indices.clear();
+        self.items.clear();
+    }
+
+    /// Uploads and draws everything queued, returning the number of draw calls. The queue is
+    /// emptied even if drawing fails.
+    pub fn flush(&mut self, backend: &mut dyn RenderBackend) -> Result<usize, RenderError> {
+        let result = self.draw(backend);
+        self.clear();
+
+        result
+    }
+
+    fn draw(&mut self, backend: &mut dyn RenderBackend) -> Result<usize, RenderError> {
+        if self.items.is_empty() {
+            return Ok(0);
+        }
+
+        // stable, so sprites sharing order and state keep their submission order
+        self.items.sort_by(|a, b| a.order.compare(&b.order).then_with(|| a.key.sort_key().cmp(&b.key.sort_key())));
+        let batches = self.build();
+
+        self.reserve(backend, self.vertices.len(), self.sorted_indices.len())?;
+        backend.buffer_sub_data(gl::ARRAY_BUFFER, self.vbo.id(), 0, as_bytes(self.interleaved.as_slice()));
+        backend.buffer_sub_data(gl::ELEMENT_ARRAY_BUFFER, self.ibo.id(), 0, as_bytes(self.sorted_indices.as_slice()));
+
+        backend.bind_vertex_array(self.vao.id());
+        backend.bind_buffer(gl::ELEMENT_ARRAY_BUFFER, self.ibo.id());
+
+        for batch in &batches {
+            let program = &batch.key.program;
+            backend.use_program(program.id());
+            // vertices are already in world space and UV offsets are baked in
+            if program.uniform("Model").is_some() {
+                program.set_uniform(backend, "Model", &glm::Mat4::identity())?;
+            }
+
+            let (src, dst) = batch.key.blend.factors();
+            backend.enable_blend(src, dst);
+            backend.bind_texture_unit(gl::TEXTURE0, batch.key.texture);
+            backend.draw_elements(batch.key.primitive.mode(), batch.index_count as i32, gl::UNSIGNED_INT,
+                batch.first_index * ::std::mem::size_of::<u32>());
+        }
+
+        backend.unbind_buffer(gl::ELEMENT_ARRAY_BUFFER);
+        backend.unbind_texture();
+        backend.unbind_vertex_array();
+
+        Ok(batches.len())
+    }
+
+    /// Lays the queued sprites out in sorted order and splits them into runs of equal state.
+    fn build(&mut self) -> Vec<Batch> {
+        self.interleaved.clear();
+        self.sorted_indices.clear();
+
+        let mut batches: Vec<Batch> = vec![];
+        let mut base = 0;
+        for item in &self.items {
+            for vertex in &self.vertices[item.first_vertex..item.first_vertex + item.vertex_count] {
+                self.interleaved.extend_from_slice(vertex.position.as_slice());
+                self.interleaved.extend_from_slice(vertex.uv.as_slice());
+                self.interleaved.extend_from_slice(vertex.color.as_slice());
+            }
+            let first_index = self.sorted_indices.len();
+            self.sorted_indices.extend(self.indices[item.first_index..item.first_index + item.index_count].iter()
+                .map(|index| index + base));
+            base += item.vertex_count as u32;
+
+            match batches.last_mut() {
+                Some(batch) if batch.key.sort_key() == item.key.sort_key() => batch.index_count += item.index_count,
+                _ => batches.push(Batch { key: item.key.clone(), first_index, index_count: item.index_count }),
+            }
+        }
+
+        batches
+    }
+
+    fn reserve(&mut self, backend: &mut dyn RenderBackend, vertices: usize, indices: usize) -> Result<(), RenderError> {
+        if vertices > self.capacity || self.vao.id() == 0 {
+            let capacity = vertices.next_power_of_two().max(1024);
+            let layout = VertexLayout::sprite();
+            self.vbo = Buffer::new(backend, &vec![0; capacity * layout.stride()], gl::ARRAY_BUFFER, BufferUsage::Stream)?;
+
+            self.vao = VertexArray::new(backend)?;
+            backend.bind_vertex_array(self.vao.id());
+            layout.apply(backend, self.vbo.id());
+            backend.unbind_vertex_array();
+
+            self.capacity = capacity;
+        }
+
+        if indices > self.index_capacity {
+            let capacity = indices.next_power_of_two().max(1536);
+            self.ibo = Buffer::new(backend, as_bytes(vec![0u32; capacity].as_slice()), gl::ELEMENT_ARRAY_BUFFER, BufferUsage::Stream)?;
+            self.index_capacity = capacity;
+        }
+
+        Ok(())
+    }
+}
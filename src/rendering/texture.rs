@@ -1,73 +1,106 @@
 use gl;
-use crate::rendering::get_uniform_location;
+use crate::rendering::{RenderBackend, RenderError, Program, TextureUnit, TextureDescriptor, TextureFormat, as_bytes};
+use crate::rendering::backend::{GpuObject, ReleaseQueue};
 use stb_image::image::{load, LoadResult};
 
+/// Owned 2D texture, deleted once dropped. Not `Clone`; wrap it in an `Arc` to share it.
 #[derive(Default, Debug)]
 pub struct Texture {
     pub index: gl::types::GLuint,
     pub width: usize,
-    pub height: usize
+    pub height: usize,
+    pub descriptor: TextureDescriptor,
+    pub format: TextureFormat,
+    release_queue: ReleaseQueue,
 }
 
-pub fn load_texture(path: &str) -> Result<Texture, String> {
-    
-    let result_texture = load(path);
-    let mut mode = gl::RGB;
-    let texture_index = gen_texture()?;
-    bind_texture(texture_index);
+// core since 4.6, identical to the EXT_texture_filter_anisotropic enums
+const TEXTURE_MAX_ANISOTROPY: gl::types::GLenum = 0x84FE;
+const MAX_TEXTURE_MAX_ANISOTROPY: gl::types::GLenum = 0x84FF;
 
-    set_texture_filter(gl::NEAREST);
+impl Drop for Texture {
+    fn drop(&mut self) {
+        if self.index != 0 {
+            self.release_queue.release(GpuObject::Texture(self.index));
+        }
+    }
+}
 
-    let mut result = Texture {
-        index: texture_index,
-        ..Default::default()
+pub fn load_texture(backend: &mut dyn RenderBackend, path: &str, descriptor: &TextureDescriptor) -> Result<Texture, RenderError> {
+    
+    let result_texture = load(path);
+    let unsupported = |depth: usize| RenderError::ImageDecode {
+        path: path.to_string(),
+        message: format!("unsupported channel count {}", depth),
     };
 
-    match result_texture {
+    // decode before creating the texture so a bad file doesn't leave an empty texture behind
+    let (width, height, format, data) = match &result_texture {
         LoadResult::Error(msg) => {
-            println!("Error: {}", msg);
-            return Err(msg);
+            return Err(RenderError::ImageDecode { path: path.to_string(), message: msg.clone() });
         },
         LoadResult::ImageU8(img) => {
-            println!("ImageU8: h:{}, w:{}, d:{}", img.height, img.width, img.depth);
-            result.width = img.width;
-            result.height = img.height;
-
-            if img.depth == 4 {
-                mode = gl::RGBA;
-            }
-
-            set_texture_2d::<u8>(mode, img.width as i32, img.height as i32, gl::UNSIGNED_BYTE, &img.data[..]);
+            let format = TextureFormat::from_u8(img.depth, descriptor).ok_or_else(|| unsupported(img.depth))?;
+            (img.width, img.height, format, &img.data[..])
         },
         LoadResult::ImageF32(img) => {
-            println!("ImageF32: h:{}, w:{}", img.height, img.width);
-            result.width = img.width;
-            result.height = img.height;
-            
-            if img.depth == 4 {
-                mode = gl::RGBA;
-            }
-
-            set_texture_2d::<f32>(mode, img.width as i32, img.height as i32, gl::FLOAT, &img.data[..]);
+            let format = TextureFormat::from_f32(img.depth, descriptor).ok_or_else(|| unsupported(img.depth))?;
+            (img.width, img.height, format, as_bytes(&img.data[..]))
         }
+    };
+
+    create_texture(backend, width, height, format, descriptor, data)
+}
+
+/// Uploads tightly packed pixels laid out as `format` into a new texture. A mipmap min filter
+/// without `descriptor.mipmaps` is rejected, the texture would be incomplete and sample black.
+pub fn create_texture(backend: &mut dyn RenderBackend, width: usize, height: usize, format: TextureFormat,
+    descriptor: &TextureDescriptor, data: &[u8]) -> Result<Texture, RenderError> {
+    if descriptor.samples_mipmaps() && !descriptor.mipmaps {
+        return Err(RenderError::IncompleteTexture { min_filter: descriptor.min_filter });
+    }
+
+    let texture_index = backend.gen_texture()?;
+    backend.bind_texture(texture_index);
+
+    backend.set_texture_sampling(descriptor);
+    if format.is_swizzled() {
+        backend.set_texture_swizzle(format.swizzle);
+    }
+
+    // rows are tightly packed, which the default alignment of 4 only covers for some widths
+    backend.set_pixel_store_mode(gl::UNPACK_ALIGNMENT, format.unpack_alignment(width));
+    backend.set_texture_2d(format.internal_format, format.format, width as i32, height as i32, format.data_type, data);
+    backend.set_pixel_store_mode(gl::UNPACK_ALIGNMENT, 4);
+
+    if descriptor.mipmaps {
+        backend.generate_mipmap();
     }
 
-    Ok(result)
+    Ok(Texture {
+        index: texture_index,
+        width,
+        height,
+        descriptor: *descriptor,
+        format,
+        release_queue: backend.release_queue(),
+    })
 }
 
-pub fn set_texture_to_program(active_texture: gl::types::GLenum, texture: gl::types::GLuint, 
-    program: gl::types::GLuint, uniform_name: &str) {
-    bind_texture_unit(active_texture, texture);
-    let uniform_location = get_uniform_location(program, uniform_name).unwrap();
+pub fn set_texture_to_program(backend: &mut dyn RenderBackend, active_texture: gl::types::GLenum, texture: gl::types::GLuint, 
+    program: &Program, uniform_name: &str) -> Result<(), RenderError> {
+    let unit = TextureUnit::from_gl(active_texture).ok_or(RenderError::InvalidTextureUnit { active_texture })?;
+    backend.bind_texture_unit(active_texture, texture);
     
-    unsafe {
-        gl::Uniform1i(uniform_location, 0);
-    }
+    backend.use_program(program.id());
+    program.set_uniform(backend, uniform_name, &unit)?;
+
+    backend.unbind_texture();
 
-    unbind_texture();
+    Ok(())
 }
 
-pub fn gen_texture() -> Result<gl::types::GLuint, String> {
+pub fn gen_texture() -> Result<gl::types::GLuint, RenderError> {
     let mut texture_index: gl::types::GLuint = 0;
 
     unsafe {
@@ -77,6 +110,12 @@ pub fn gen_texture() -> Result<gl::types::GLuint, String> {
     Ok(texture_index)
 }
 
+pub fn delete_texture(texture: gl::types::GLuint) {
+    unsafe {
+        gl::DeleteTextures(1, &texture);
+    }
+}
+
 pub fn bind_texture(texture: gl::types::GLuint) {
     unsafe {
         gl::BindTexture(gl::TEXTURE_2D, texture);
@@ -98,7 +137,7 @@ pub fn unbind_texture() {
     }
 }
 
-pub fn get_current_bound_texture() -> Result<gl::types::GLint, String> {
+pub fn get_current_bound_texture() -> Result<gl::types::GLint, RenderError> {
     let mut current_texture: gl::types::GLint = 0;
     
     unsafe {
@@ -115,24 +154,87 @@ pub fn set_texture_filter(filter: gl::types::GLenum) {
     }
 }
 
-pub fn set_pixel_store_mode(mode: gl::types::GLenum) {
+pub fn set_texture_sampling(descriptor: &TextureDescriptor) {
+    unsafe {
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, descriptor.min_filter as i32);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, descriptor.mag_filter as i32);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, descriptor.wrap_s as i32);
+        gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, descriptor.wrap_t as i32);
+
+        if descriptor.anisotropy > 1 {
+            let mut max_anisotropy: gl::types::GLfloat = 1.0;
+            gl::GetFloatv(MAX_TEXTURE_MAX_ANISOTROPY, &mut max_anisotropy);
+            // drivers without the extension leave it at 1 and ignore the parameter
+            gl::TexParameterf(gl::TEXTURE_2D, TEXTURE_MAX_ANISOTROPY, (descriptor.anisotropy as f32).min(max_anisotropy));
+        }
+    }
+}
+
+pub fn generate_mipmap() {
     unsafe {
-        gl::PixelStorei(gl::UNPACK_ROW_LENGTH, 0);
+        gl::GenerateMipmap(gl::TEXTURE_2D);
     }
 }
 
-pub fn set_texture_2d<T>(mode: gl::types::GLenum, width: i32, height: i32, data_type: gl::types::GLenum, data: &[T]) {
+pub fn set_pixel_store_mode(mode: gl::types::GLenum, value: gl::types::GLint) {
+    unsafe {
+        gl::PixelStorei(mode, value);
+    }
+}
+
+pub fn set_texture_swizzle(swizzle: [gl::types::GLenum; 4]) {
+    let swizzle = swizzle.map(|channel| channel as gl::types::GLint);
+    unsafe {
+        gl::TexParameteriv(gl::TEXTURE_2D, gl::TEXTURE_SWIZZLE_RGBA, swizzle.as_ptr());
+    }
+}
+
+/// Uploads `data`, or only allocates storage when it's empty (e.g. for a render target).
+pub fn set_texture_2d<T>(internal_format: gl::types::GLenum, mode: gl::types::GLenum, width: i32, height: i32, data_type: gl::types::GLenum, data: &[T]) {
+    let pixels = if data.is_empty() { std::ptr::null() } else { data.as_ptr() as *const gl::types::GLvoid };
     unsafe {
         gl::TexImage2D(
             gl::TEXTURE_2D, 
             0, 
-            mode as i32, 
+            internal_format as i32, 
             width, 
             height, 
             0, 
             mode, 
             data_type, 
-            data.as_ptr() as *const gl::types::GLvoid
+            pixels
         );
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rendering::RecordingBackend;
+
+    #[test]
+    fn mipmap_filter_without_mipmaps_is_rejected() {
+        let mut backend = RecordingBackend::new();
+        let log = backend.log();
+        let descriptor = TextureDescriptor::default().with_filter(gl::LINEAR_MIPMAP_LINEAR, gl::LINEAR);
+        let format = TextureFormat::rgba8();
+
+        let result = create_texture(&mut backend, 1, 1, format, &descriptor, &[255; 4]);
+        assert!(matches!(result, Err(RenderError::IncompleteTexture { min_filter: gl::LINEAR_MIPMAP_LINEAR })));
+        assert!(log.commands().is_empty());
+
+        let descriptor = descriptor.with_mipmaps(true);
+        assert!(create_texture(&mut backend, 1, 1, format, &descriptor, &[255; 4]).is_ok());
+    }
+
+    #[test]
+    fn unit_indices_are_rejected_instead_of_wrapping() {
+        let mut backend = RecordingBackend::new();
+        let log = backend.log();
+        let program = Program::default();
+
+        let result = set_texture_to_program(&mut backend, 0, 7, &program, "Texture");
+        assert!(matches!(result, Err(RenderError::InvalidTextureUnit { active_texture: 0 })));
+        assert!(log.commands().is_empty());
+        assert_eq!(TextureUnit::from_gl(gl::TEXTURE3), Some(TextureUnit(3)));
+    }
+}
@@ -0,0 +1,94 @@
+use gl;
+
+/// How a texture is sampled and stored: filters, wrap modes, mipmaps, anisotropic filtering
+/// and whether its texels are sRGB encoded. The default matches pixel art on a repeating texture.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TextureDescriptor {
+    pub min_filter: gl::types::GLenum,
+    pub mag_filter: gl::types::GLenum,
+    pub wrap_s: gl::types::GLenum,
+    pub wrap_t: gl::types::GLenum,
+    pub mipmaps: bool,
+    /// Maximum anisotropy; 1 disables anisotropic filtering.
+    pub anisotropy: u32,
+    pub srgb: bool,
+    /// Keep float images at 32 bits per channel instead of half floats.
+    pub float32: bool,
+}
+
+impl Default for TextureDescriptor {
+    fn default() -> Self {
+        TextureDescriptor {
+            min_filter: gl::NEAREST,
+            mag_filter: gl::NEAREST,
+            wrap_s: gl::REPEAT,
+            wrap_t: gl::REPEAT,
+            mipmaps: false,
+            anisotropy: 1,
+            srgb: false,
+            float32: false,
+        }
+    }
+}
+
+impl TextureDescriptor {
+    /// Crisp texels and no bleeding from the opposite edge.
+    pub fn pixel_art() -> Self {
+        TextureDescriptor::default().with_wrap(gl::CLAMP_TO_EDGE, gl::CLAMP_TO_EDGE)
+    }
+
+    /// Filtered, mipmapped art such as UI elements that are drawn scaled.
+    pub fn smooth() -> Self {
+        TextureDescriptor::default()
+            .with_filter(gl::LINEAR_MIPMAP_LINEAR, gl::LINEAR)
+            .with_wrap(gl::CLAMP_TO_EDGE, gl::CLAMP_TO_EDGE)
+            .with_mipmaps(true)
+    }
+
+    /// Filtered, mipmapped backgrounds repeated across large surfaces.
+    pub fn tiling() -> Self {
+        TextureDescriptor::default()
+            .with_filter(gl::LINEAR_MIPMAP_LINEAR, gl::LINEAR)
+            .with_mipmaps(true)
+            .with_anisotropy(4)
+    }
+
+    /// Whether `min_filter` reads from the mip chain, which leaves the texture incomplete
+    /// unless `mipmaps` is set.
+    pub fn samples_mipmaps(&self) -> bool {
+        matches!(self.min_filter, gl::NEAREST_MIPMAP_NEAREST | gl::LINEAR_MIPMAP_NEAREST
+            | gl::NEAREST_MIPMAP_LINEAR | gl::LINEAR_MIPMAP_LINEAR)
+    }
+
+    pub fn with_filter(mut self, min_filter: gl::types::GLenum, mag_filter: gl::types::GLenum) -> Self {
+        self.min_filter = min_filter;
+        self.mag_filter = mag_filter;
+        self
+    }
+
+    pub fn with_wrap(mut self, wrap_s: gl::types::GLenum, wrap_t: gl::types::GLenum) -> Self {
+        self.wrap_s = wrap_s;
+        self.wrap_t = wrap_t;
+        self
+    }
+
+    pub fn with_mipmaps(mut self, mipmaps: bool) -> Self {
+        self.mipmaps = mipmaps;
+        self
+    }
+
+    pub fn with_anisotropy(mut self, anisotropy: u32) -> Self {
+        self.anisotropy = anisotropy.max(1);
+        self
+    }
+
+    pub fn with_srgb(mut self, srgb: bool) -> Self {
+        self.srgb = srgb;
+        self
+    }
+
+    pub fn with_float32(mut self, float32: bool) -> Self {
+        self.float32 = float32;
+        self
+    }
+}
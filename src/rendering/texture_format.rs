@@ -0,0 +1,93 @@
+use gl;
+use crate::rendering::TextureDescriptor;
+
+const SWIZZLE_IDENTITY: [gl::types::GLenum; 4] = [gl::RED, gl::GREEN, gl::BLUE, gl::ALPHA];
+// one channel: grey, opaque
+const SWIZZLE_GREY: [gl::types::GLenum; 4] = [gl::RED, gl::RED, gl::RED, gl::ONE];
+// two channels: grey plus alpha
+const SWIZZLE_GREY_ALPHA: [gl::types::GLenum; 4] = [gl::RED, gl::RED, gl::RED, gl::GREEN];
+
+/// How decoded texels are laid out in memory and stored on the GPU.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextureFormat {
+    pub internal_format: gl::types::GLenum,
+    pub format: gl::types::GLenum,
+    pub data_type: gl::types::GLenum,
+    pub swizzle: [gl::types::GLenum; 4],
+}
+
+impl Default for TextureFormat {
+    fn default() -> Self {
+        TextureFormat::rgba8()
+    }
+}
+
+impl TextureFormat {
+    pub fn rgba8() -> Self {
+        TextureFormat {
+            internal_format: gl::RGBA8,
+            format: gl::RGBA,
+            data_type: gl::UNSIGNED_BYTE,
+            swizzle: SWIZZLE_IDENTITY,
+        }
+    }
+
+    /// Format for `channels` tightly packed 8-bit values. sRGB only exists for colour images,
+    /// so grey and grey-alpha stay linear.
+    pub fn from_u8(channels: usize, descriptor: &TextureDescriptor) -> Option<Self> {
+        let (internal_format, format, swizzle) = match channels {
+            1 => (gl::R8, gl::RED, SWIZZLE_GREY),
+            2 => (gl::RG8, gl::RG, SWIZZLE_GREY_ALPHA),
+            3 if descriptor.srgb => (gl::SRGB8, gl::RGB, SWIZZLE_IDENTITY),
+            3 => (gl::RGB8, gl::RGB, SWIZZLE_IDENTITY),
+            4 if descriptor.srgb => (gl::SRGB8_ALPHA8, gl::RGBA, SWIZZLE_IDENTITY),
+            4 => (gl::RGBA8, gl::RGBA, SWIZZLE_IDENTITY),
+            _ => return None,
+        };
+
+        Some(TextureFormat { internal_format, format, data_type: gl::UNSIGNED_BYTE, swizzle })
+    }
+
+    /// Format for `channels` 32-bit floats, e.g. from an `.hdr` file. Stored as half floats
+    /// unless the descriptor asks for full precision; always linear.
+    pub fn from_f32(channels: usize, descriptor: &TextureDescriptor) -> Option<Self> {
+        let (half, full, format, swizzle) = match channels {
+            1 => (gl::R16F, gl::R32F, gl::RED, SWIZZLE_GREY),
+            2 => (gl::RG16F, gl::RG32F, gl::RG, SWIZZLE_GREY_ALPHA),
+            3 => (gl::RGB16F, gl::RGB32F, gl::RGB, SWIZZLE_IDENTITY),
+            4 => (gl::RGBA16F, gl::RGBA32F, gl::RGBA, SWIZZLE_IDENTITY),
+            _ => return None,
+        };
+        let internal_format = if descriptor.float32 { full } else { half };
+
+        Some(TextureFormat { internal_format, format, data_type: gl::FLOAT, swizzle })
+    }
+
+    pub fn channels(&self) -> usize {
+        match self.format {
+            gl::RED => 1,
+            gl::RG => 2,
+            gl::RGB => 3,
+            _ => 4,
+        }
+    }
+
+    pub fn bytes_per_pixel(&self) -> usize {
+        let channel_size = match self.data_type {
+            gl::FLOAT => 4,
+            gl::HALF_FLOAT | gl::UNSIGNED_SHORT => 2,
+            _ => 1,
+        };
+        self.channels() * channel_size
+    }
+
+    /// Largest `GL_UNPACK_ALIGNMENT` that rows of `width` tightly packed pixels satisfy.
+    pub fn unpack_alignment(&self, width: usize) -> i32 {
+        let row = width * self.bytes_per_pixel();
+        [8, 4, 2].iter().copied().find(|&alignment| row.is_multiple_of(alignment)).unwrap_or(1) as i32
+    }
+
+    pub fn is_swizzled(&self) -> bool {
+        self.swizzle != SWIZZLE_IDENTITY
+    }
+}
@@ -1,11 +1,7 @@
-use gl;
-use crate::rendering::get_uniform_location;
-use glm::{TMat4, value_ptr};
+use glm::{vec3, TMat4, Vec3};
 
-pub fn set_mvp_to_program(mvp: &TMat4<f32>, program: gl::types::GLuint, uniform_name: &str) {
-    
-    let uniform_location = get_uniform_location(program, uniform_name).unwrap();
-    unsafe {
-        gl::UniformMatrix4fv(uniform_location, 1, gl::FALSE, value_ptr(mvp).as_ptr());
-    }
-}
\ No newline at end of file
+pub fn model_matrix(position: &Vec3, rotation_rad: f32, scale: &Vec3) -> TMat4<f32> {
+    let mut model_matrix = glm::translation(position);
+    model_matrix = glm::rotate(&model_matrix, rotation_rad, &vec3(0., 0., 1.));
+    glm::scale(&model_matrix, scale)
+}
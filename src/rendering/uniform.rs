@@ -0,0 +1,116 @@
+use std::borrow::Cow;
+use glm::{Vec2, Vec3, Vec4, Mat3, Mat4};
+use gl;
+
+/// A texture unit index as seen by a `sampler2D` uniform, i.e. `TextureUnit(1)` for `gl::TEXTURE1`.
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+pub struct TextureUnit(pub u32);
+
+impl TextureUnit {
+    /// The unit `active_texture` selects, `None` for a value below `gl::TEXTURE0` such as a
+    /// plain unit index.
+    pub fn from_gl(active_texture: gl::types::GLenum) -> Option<TextureUnit> {
+        active_texture.checked_sub(gl::TEXTURE0).map(TextureUnit)
+    }
+}
+
+/// Data for one `glUniform*` call. Borrowed while being set, owned when it is kept around
+/// (e.g. in a `CommandLog`).
+#[derive(Debug, Clone, PartialEq)]
+pub enum UniformValue<'a> {
+    Float(Cow<'a, [f32]>),
+    Vec2(Cow<'a, [Vec2]>),
+    Vec3(Cow<'a, [Vec3]>),
+    Vec4(Cow<'a, [Vec4]>),
+    Int(Cow<'a, [i32]>),
+    Bool(Cow<'a, [bool]>),
+    Mat3(Cow<'a, [Mat3]>),
+    Mat4(Cow<'a, [Mat4]>),
+    Sampler(Cow<'a, [TextureUnit]>),
+}
+
+impl<'a> UniformValue<'a> {
+    pub fn into_owned(self) -> UniformValue<'static> {
+        match self {
+            UniformValue::Float(v) => UniformValue::Float(Cow::Owned(v.into_owned())),
+            UniformValue::Vec2(v) => UniformValue::Vec2(Cow::Owned(v.into_owned())),
+            UniformValue::Vec3(v) => UniformValue::Vec3(Cow::Owned(v.into_owned())),
+            UniformValue::Vec4(v) => UniformValue::Vec4(Cow::Owned(v.into_owned())),
+            UniformValue::Int(v) => UniformValue::Int(Cow::Owned(v.into_owned())),
+            UniformValue::Bool(v) => UniformValue::Bool(Cow::Owned(v.into_owned())),
+            UniformValue::Mat3(v) => UniformValue::Mat3(Cow::Owned(v.into_owned())),
+            UniformValue::Mat4(v) => UniformValue::Mat4(Cow::Owned(v.into_owned())),
+            UniformValue::Sampler(v) => UniformValue::Sampler(Cow::Owned(v.into_owned())),
+        }
+    }
+
+    /// Number of elements, i.e. the `count` of the `glUniform*v` call.
+    pub fn len(&self) -> usize {
+        match self {
+            UniformValue::Float(v) => v.len(),
+            UniformValue::Vec2(v) => v.len(),
+            UniformValue::Vec3(v) => v.len(),
+            UniformValue::Vec4(v) => v.len(),
+            UniformValue::Int(v) => v.len(),
+            UniformValue::Bool(v) => v.len(),
+            UniformValue::Mat3(v) => v.len(),
+            UniformValue::Mat4(v) => v.len(),
+            UniformValue::Sampler(v) => v.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Anything that can be written to a uniform with `Program::set_uniform`.
+pub trait Uniform {
+    /// The type the uniform has to be declared with in GLSL, as reported by reflection.
+    fn gl_type(&self) -> gl::types::GLenum;
+    fn value(&self) -> UniformValue<'_>;
+}
+
+macro_rules! impl_uniform {
+    ($type:ty, $gl_type:expr, $variant:ident) => {
+        impl Uniform for $type {
+            fn gl_type(&self) -> gl::types::GLenum {
+                $gl_type
+            }
+
+            fn value(&self) -> UniformValue<'_> {
+                UniformValue::$variant(Cow::Borrowed(std::slice::from_ref(self)))
+            }
+        }
+
+        impl Uniform for [$type] {
+            fn gl_type(&self) -> gl::types::GLenum {
+                $gl_type
+            }
+
+            fn value(&self) -> UniformValue<'_> {
+                UniformValue::$variant(Cow::Borrowed(self))
+            }
+        }
+
+        impl Uniform for Vec<$type> {
+            fn gl_type(&self) -> gl::types::GLenum {
+                $gl_type
+            }
+
+            fn value(&self) -> UniformValue<'_> {
+                UniformValue::$variant(Cow::Borrowed(self.as_slice()))
+            }
+        }
+    };
+}
+
+impl_uniform!(f32, gl::FLOAT, Float);
+impl_uniform!(Vec2, gl::FLOAT_VEC2, Vec2);
+impl_uniform!(Vec3, gl::FLOAT_VEC3, Vec3);
+impl_uniform!(Vec4, gl::FLOAT_VEC4, Vec4);
+impl_uniform!(i32, gl::INT, Int);
+impl_uniform!(bool, gl::BOOL, Bool);
+impl_uniform!(Mat3, gl::FLOAT_MAT3, Mat3);
+impl_uniform!(Mat4, gl::FLOAT_MAT4, Mat4);
+impl_uniform!(TextureUnit, gl::SAMPLER_2D, Sampler);
@@ -0,0 +1,123 @@
+use gl;
+use glm::{Vec2, Vec3, Vec4, Mat3, Mat4};
+use crate::rendering::{RenderBackend, RenderError, Buffer, BufferUsage};
+
+/// Name of the per-frame block declared in `shaders/frame_data.glsl`.
+pub const FRAME_DATA_BLOCK: &str = "FrameData";
+pub const FRAME_DATA_BINDING: gl::types::GLuint = 0;
+
+/// Packs values with the std140 layout rules: scalars align to 4 bytes, `vec2` to 8,
+/// `vec3`/`vec4` and every matrix column to 16.
+#[derive(Default, Debug, Clone)]
+pub struct Std140Writer {
+    data: Vec<u8>,
+}
+
+impl Std140Writer {
+    pub fn new() -> Std140Writer {
+        Std140Writer::default()
+    }
+
+    fn align(&mut self, alignment: usize) {
+        let padded = self.data.len().next_multiple_of(alignment);
+        self.data.resize(padded, 0);
+    }
+
+    fn put(&mut self, values: &[f32]) {
+        for value in values {
+            self.data.extend_from_slice(&value.to_ne_bytes());
+        }
+    }
+
+    pub fn write_f32(&mut self, value: f32) -> &mut Self {
+        self.align(4);
+        self.put(&[value]);
+        self
+    }
+
+    pub fn write_vec2(&mut self, value: &Vec2) -> &mut Self {
+        self.align(8);
+        self.put(value.as_slice());
+        self
+    }
+
+    pub fn write_vec3(&mut self, value: &Vec3) -> &mut Self {
+        self.align(16);
+        self.put(value.as_slice());
+        self
+    }
+
+    pub fn write_vec4(&mut self, value: &Vec4) -> &mut Self {
+        self.align(16);
+        self.put(value.as_slice());
+        self
+    }
+
+    pub fn write_mat3(&mut self, value: &Mat3) -> &mut Self {
+        for column in 0..3 {
+            self.align(16);
+            self.put(value.column(column).as_slice());
+        }
+        self
+    }
+
+    pub fn write_mat4(&mut self, value: &Mat4) -> &mut Self {
+        self.align(16);
+        self.put(value.as_slice());
+        self
+    }
+
+    /// The packed bytes, padded to a whole number of `vec4`s.
+    pub fn finish(&mut self) -> Vec<u8> {
+        self.align(16);
+        std::mem::take(&mut self.data)
+    }
+}
+
+/// Contents of the `FrameData` block, filled once per frame by `Render`.
+#[derive(Debug, Clone)]
+pub struct FrameData {
+    pub projection: Mat4,
+    pub view: Mat4,
+    pub viewport_size: Vec2,
+    pub time: f32,
+}
+
+impl FrameData {
+    pub fn to_std140(&self) -> Vec<u8> {
+        Std140Writer::new()
+            .write_mat4(&self.projection)
+            .write_mat4(&self.view)
+            .write_vec2(&self.viewport_size)
+            .write_f32(self.time)
+            .finish()
+    }
+}
+
+/// A uniform buffer attached to a fixed binding point; programs are pointed at the same
+/// binding with `Program::bind_uniform_block`.
+#[derive(Default, Debug)]
+pub struct UniformBlock {
+    buffer: Buffer,
+    binding: gl::types::GLuint,
+}
+
+impl UniformBlock {
+    pub fn new(backend: &mut dyn RenderBackend, binding: gl::types::GLuint, size: usize) -> Result<UniformBlock, RenderError> {
+        let buffer = Buffer::new(backend, &vec![0; size], gl::UNIFORM_BUFFER, BufferUsage::Dynamic)?;
+
+        Ok(UniformBlock { buffer, binding })
+    }
+
+    pub fn binding(&self) -> gl::types::GLuint {
+        self.binding
+    }
+
+    pub fn update(&self, backend: &mut dyn RenderBackend, data: &[u8]) {
+        backend.buffer_sub_data(gl::UNIFORM_BUFFER, self.buffer.id(), 0, data);
+    }
+
+    pub fn bind(&self, backend: &mut dyn RenderBackend) {
+        backend.bind_buffer_base(gl::UNIFORM_BUFFER, self.binding, self.buffer.id());
+    }
+}
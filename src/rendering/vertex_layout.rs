@@ -0,0 +1,235 @@
+use gl;
+use glm::{vec4, Vec4};
+use crate::rendering::{RenderBackend, RenderError, Program};
+
+/// One input of an interleaved vertex, e.g. `Position` as three floats at location 0.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VertexAttribute {
+    pub name: String,
+    pub location: gl::types::GLuint,
+    /// Component type such as `gl::FLOAT` or `gl::UNSIGNED_BYTE`.
+    pub type_: gl::types::GLenum,
+    pub count: usize,
+    /// Integer components are mapped to 0..1 (or -1..1) instead of converted as-is.
+    pub normalized: bool,
+}
+
+impl VertexAttribute {
+    pub fn new(name: &str, location: gl::types::GLuint, type_: gl::types::GLenum, count: usize, normalized: bool) -> Self {
+        VertexAttribute { name: name.to_string(), location, type_, count, normalized }
+    }
+
+    pub fn size(&self) -> usize {
+        component_size(self.type_) * self.count
+    }
+}
+
+/// Describes how the attributes of one vertex are laid out in an interleaved buffer, in
+/// declaration order and without padding. `divisor` is 0 for per-vertex data, or the number
+/// of instances each element is repeated for.
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct VertexLayout {
+    pub attributes: Vec<VertexAttribute>,
+    pub divisor: gl::types::GLuint,
+}
+
+impl VertexLayout {
+    pub fn new() -> Self {
+        VertexLayout::default()
+    }
+
+    /// `Position` (3 floats), `TexCoord` (2) and `Color` (4) at locations 0-2, matching
+    /// `shaders/common.glsl`.
+    pub fn sprite() -> Self {
+        VertexLayout::new()
+            .with_attribute("Position", 0, gl::FLOAT, 3, false)
+            .with_attribute("TexCoord", 1, gl::FLOAT, 2, false)
+            .with_attribute("Color", 2, gl::FLOAT, 4, false)
+    }
+
+    pub fn with_attribute(mut self, name: &str, location: gl::types::GLuint, type_: gl::types::GLenum,
+        count: usize, normalized: bool) -> Self {
+        self.attributes.push(VertexAttribute::new(name, location, type_, count, normalized));
+        self
+    }
+
+    pub fn with_divisor(mut self, divisor: gl::types::GLuint) -> Self {
+        self.divisor = divisor;
+        self
+    }
+
+    pub fn stride(&self) -> usize {
+        self.attributes.iter().map(|attribute| attribute.size()).sum()
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&VertexAttribute> {
+        self.attributes.iter().find(|attribute| attribute.name == name)
+    }
+
+    /// Byte offset of `name` within a vertex.
+    pub fn offset(&self, name: &str) -> Option<usize> {
+        let index = self.attributes.iter().position(|attribute| attribute.name == name)?;
+        Some(self.attributes[..index].iter().map(|attribute| attribute.size()).sum())
+    }
+
+    pub fn vertex_count(&self, data: &[u8]) -> usize {
+        match self.stride() {
+            0 => 0,
+            stride => data.len() / stride,
+        }
+    }
+
+    /// Appends one vertex to `data`. `values[i]` fills attribute `i`; missing components are 0.
+    pub fn write(&self, data: &mut Vec<u8>, values: &[&[f32]]) {
+        for (index, attribute) in self.attributes.iter().enumerate() {
+            let values = values.get(index).copied().unwrap_or(&[]);
+            for component in 0..attribute.count {
+                let value = values.get(component).copied().unwrap_or(0.0);
+                encode_component(data, attribute.type_, attribute.normalized, value);
+            }
+        }
+    }
+
+    /// Overwrites attribute `name` of vertex `vertex` in place. Returns false if there is no
+    /// such attribute or vertex.
+    pub fn set(&self, data: &mut [u8], vertex: usize, name: &str, values: &[f32]) -> bool {
+        let (attribute, offset) = match (self.attribute(name), self.offset(name)) {
+            (Some(attribute), Some(offset)) => (attribute, vertex * self.stride() + offset),
+            _ => return false,
+        };
+        let target = match data.get_mut(offset..offset + attribute.size()) {
+            Some(target) => target,
+            None => return false,
+        };
+
+        let mut encoded = Vec::with_capacity(attribute.size());
+        for component in 0..attribute.count {
+            encode_component(&mut encoded, attribute.type_, attribute.normalized, values.get(component).copied().unwrap_or(0.0));
+        }
+        target.copy_from_slice(&encoded);
+
+        true
+    }
+
+    /// Attribute `name` of vertex `vertex`, with missing components filled like GL does (0, 0, 0, 1).
+    pub fn read(&self, data: &[u8], vertex: usize, name: &str) -> Option<Vec4> {
+        self.reader(name)?.read(data, vertex)
+    }
+
+    /// Resolves attribute `name` once for reading it from many vertices.
+    pub fn reader(&self, name: &str) -> Option<AttributeReader> {
+        let attribute = self.attribute(name)?;
+        Some(AttributeReader {
+            offset: self.offset(name)?,
+            stride: self.stride(),
+            type_: attribute.type_,
+            count: attribute.count,
+            normalized: attribute.normalized,
+        })
+    }
+
+    /// Points the attributes of the bound vertex array at `buffer`.
+    pub fn apply(&self, backend: &mut dyn RenderBackend, buffer: gl::types::GLuint) {
+        let stride = self.stride() as gl::types::GLsizei;
+        let mut offset = 0;
+        for attribute in &self.attributes {
+            backend.vertex_attrib_pointer(buffer, attribute, stride, offset);
+            if self.divisor != 0 {
+                backend.vertex_attrib_divisor(attribute.location, self.divisor);
+            }
+            offset += attribute.size();
+        }
+    }
+
+    /// Checks the attributes `program` declares against this layout: a shared name must use the
+    /// same location and be a float input, since attributes are always fed as floats. Inputs the
+    /// layout doesn't mention are left alone, another buffer (or GL's default) may supply them.
+    pub fn validate(&self, program: &Program) -> Result<(), RenderError> {
+        for attribute in &self.attributes {
+            let active = match program.attribute(&attribute.name) {
+                Some(active) => active,
+                None => continue,
+            };
+            let mismatch = |message: String| RenderError::VertexLayout {
+                program: program.id(),
+                name: attribute.name.clone(),
+                message,
+            };
+
+            if active.location >= 0 && active.location as gl::types::GLuint != attribute.location {
+                return Err(mismatch(format!("the program reads location {}, the layout provides location {}",
+                    active.location, attribute.location)));
+            }
+            if is_integer_input(active.type_) {
+                return Err(mismatch("integer inputs can't be fed from a vertex layout".to_string()));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Where one attribute sits in an interleaved buffer, see `VertexLayout::reader`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AttributeReader {
+    offset: usize,
+    stride: usize,
+    type_: gl::types::GLenum,
+    count: usize,
+    normalized: bool,
+}
+
+impl AttributeReader {
+    /// The attribute of vertex `vertex`, with missing components filled like GL does (0, 0, 0, 1).
+    pub fn read(&self, data: &[u8], vertex: usize) -> Option<Vec4> {
+        let size = component_size(self.type_);
+        let offset = vertex * self.stride + self.offset;
+        let bytes = data.get(offset..offset + size * self.count)?;
+
+        let mut value = vec4(0., 0., 0., 1.);
+        for (component, bytes) in bytes.chunks_exact(size).take(4).enumerate() {
+            value[component] = decode_component(bytes, self.type_, self.normalized);
+        }
+        Some(value)
+    }
+}
+
+pub fn component_size(type_: gl::types::GLenum) -> usize {
+    match type_ {
+        gl::BYTE | gl::UNSIGNED_BYTE => 1,
+        gl::SHORT | gl::UNSIGNED_SHORT => 2,
+        _ => 4,
+    }
+}
+
+fn is_integer_input(type_: gl::types::GLenum) -> bool {
+    matches!(type_, gl::INT | gl::INT_VEC2 | gl::INT_VEC3 | gl::INT_VEC4
+        | gl::UNSIGNED_INT | gl::UNSIGNED_INT_VEC2 | gl::UNSIGNED_INT_VEC3 | gl::UNSIGNED_INT_VEC4)
+}
+
+fn encode_component(data: &mut Vec<u8>, type_: gl::types::GLenum, normalized: bool, value: f32) {
+    let integer = |max: f32| if normalized { (value.clamp(-1.0, 1.0) * max).round() } else { value.round() };
+    match type_ {
+        gl::BYTE => data.extend_from_slice(&(integer(127.0) as i8).to_ne_bytes()),
+        gl::UNSIGNED_BYTE => data.extend_from_slice(&(integer(255.0) as u8).to_ne_bytes()),
+        gl::SHORT => data.extend_from_slice(&(integer(32767.0) as i16).to_ne_bytes()),
+        gl::UNSIGNED_SHORT => data.extend_from_slice(&(integer(65535.0) as u16).to_ne_bytes()),
+        gl::INT => data.extend_from_slice(&(integer(2147483647.0) as i32).to_ne_bytes()),
+        gl::UNSIGNED_INT => data.extend_from_slice(&(integer(4294967295.0) as u32).to_ne_bytes()),
+        _ => data.extend_from_slice(&value.to_ne_bytes()),
+    }
+}
+
+/// Decodes one component the way GL feeds it to a float attribute.
+pub fn decode_component(bytes: &[u8], type_: gl::types::GLenum, normalized: bool) -> f32 {
+    let scale = |value: f32, max: f32| if normalized { (value / max).max(-1.0) } else { value };
+    match type_ {
+        gl::BYTE => scale(bytes[0] as i8 as f32, 127.0),
+        gl::UNSIGNED_BYTE => scale(bytes[0] as f32, 255.0),
+        gl::SHORT => scale(i16::from_ne_bytes([bytes[0], bytes[1]]) as f32, 32767.0),
+        gl::UNSIGNED_SHORT => scale(u16::from_ne_bytes([bytes[0], bytes[1]]) as f32, 65535.0),
+        gl::INT => scale(i32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f32, 2147483647.0),
+        gl::UNSIGNED_INT => scale(u32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f32, 4294967295.0),
+        _ => f32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+    }
+}
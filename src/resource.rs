@@ -2,8 +2,30 @@ pub mod projection;
 pub mod camera;
 pub mod keyboard;
 pub mod deltatime;
+pub mod elapsed_time;
+pub mod viewport;
+pub mod clear_color;
+pub mod render_device;
+pub mod shader_watcher;
+pub mod shader_library;
+pub mod texture_cache;
+pub mod post_process_stack;
+pub mod instance_groups;
+pub mod render_stats;
+pub mod screen_scaling;
 
 pub use self::projection::Projection;
 pub use self::camera::Camera;
 pub use self::keyboard::{Keyboard, KeycodeEx};
-pub use self::deltatime::DeltaTime;
\ No newline at end of file
+pub use self::deltatime::DeltaTime;
+pub use self::elapsed_time::ElapsedTime;
+pub use self::viewport::Viewport;
+pub use self::clear_color::ClearColor;
+pub use self::render_device::RenderDevice;
+pub use self::shader_watcher::{ShaderWatcher, ShaderErrors};
+pub use self::shader_library::{ShaderLibrary, ShaderKey};
+pub use self::texture_cache::TextureCache;
+pub use self::post_process_stack::{PostProcessStack, PostProcessPass};
+pub use self::instance_groups::{InstanceGroups, InstanceGroup};
+pub use self::render_stats::RenderStats;
+pub use self::screen_scaling::{ScreenScaling, ScalingMode};
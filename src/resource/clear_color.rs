@@ -0,0 +1,9 @@
+use glm::{vec4, Vec4};
+
+pub struct ClearColor(pub Vec4);
+
+impl Default for ClearColor {
+    fn default() -> Self {
+        ClearColor(vec4(0.3, 0.3, 0.5, 1.0))
+    }
+}
@@ -0,0 +1,3 @@
+/// Seconds since the main loop started.
+#[derive(Default)]
+pub struct ElapsedTime(pub f32);
@@ -0,0 +1,36 @@
+use std::collections::HashMap;
+use crate::component::{Mesh, Material};
+use crate::rendering::InstanceBuffer;
+
+/// Geometry and material shared by every `Instance` of a group. The material should use a
+/// shader with the per-instance inputs of `shaders/instanced.vs`, such as `instanced`.
+/// Groups are drawn after every sprite, lower `layer` first and then by name.
+#[derive(Default)]
+pub struct InstanceGroup {
+    pub mesh: Mesh,
+    pub material: Material,
+    pub instances: InstanceBuffer,
+    pub layer: i32,
+}
+
+impl InstanceGroup {
+    pub fn new(mesh: Mesh, material: Material) -> Self {
+        InstanceGroup { mesh, material, instances: InstanceBuffer::new(), layer: 0 }
+    }
+
+    pub fn with_layer(mut self, layer: i32) -> Self {
+        self.layer = layer;
+        self
+    }
+}
+
+/// Instance groups by name, drawn by `Render` with one instanced call each, on top of the
+/// sprites.
+#[derive(Default)]
+pub struct InstanceGroups(pub HashMap<String, InstanceGroup>);
+
+impl InstanceGroups {
+    pub fn insert(&mut self, name: &str, mesh: Mesh, material: Material) {
+        self.0.insert(name.to_string(), InstanceGroup::new(mesh, material));
+    }
+}
@@ -0,0 +1,236 @@
+use std::sync::Arc;
+use gl;
+use crate::rendering::{
+    RenderBackend,
+    RenderError,
+    RenderTarget,
+    TextureDescriptor,
+    BlendMode,
+    Preprocessor,
+    Program,
+    Buffer,
+    BufferUsage,
+    VertexArray,
+    VertexLayout,
+    as_bytes,
+    set_texture_to_program
+};
+use crate::resource::{ShaderLibrary, ShaderKey};
+
+/// One full-screen pass. Its fragment shader samples the previous pass from `Texture` and
+/// may declare `uniform float Strength` to be driven by `strength`.
+pub struct PostProcessPass {
+    pub name: String,
+    pub shader: String,
+    pub defines: Vec<String>,
+    pub enabled: bool,
+    pub strength: f32,
+    pub strength_range: (f32, f32),
+    pub program: Option<Arc<Program>>,
+}
+
+impl PostProcessPass {
+    pub fn new(name: &str, shader: &str, defines: &[&str]) -> Self {
+        PostProcessPass {
+            name: name.to_string(),
+            shader: shader.to_string(),
+            defines: defines.iter().map(|define| define.to_string()).collect(),
+            enabled: true,
+            strength: 1.0,
+            strength_range: (0.0, 1.0),
+            program: None,
+        }
+    }
+
+    pub fn with_enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+
+    pub fn with_strength(mut self, strength: f32) -> Self {
+        self.strength = strength;
+        self
+    }
+
+    pub fn with_strength_range(mut self, min: f32, max: f32) -> Self {
+        self.strength_range = (min, max);
+        self
+    }
+
+    pub fn key(&self) -> ShaderKey {
+        ShaderKey::new(&self.shader, &self.defines)
+    }
+
+    pub fn vignette() -> Self {
+        PostProcessPass::new("Vignette", "post", &["VIGNETTE"]).with_strength(0.6)
+    }
+
+    pub fn grayscale() -> Self {
+        PostProcessPass::new("Grayscale", "post", &["GRAYSCALE"])
+    }
+
+    pub fn crt() -> Self {
+        PostProcessPass::new("CRT", "post", &["CRT"]).with_strength(0.5)
+    }
+
+    /// `strength` is the block size in pixels.
+    pub fn pixelate() -> Self {
+        PostProcessPass::new("Pixelate", "post", &["PIXELATE"]).with_strength(4.0).with_strength_range(1.0, 16.0)
+    }
+}
+
+/// Quad covering clip space, drawn as a triangle fan.
+#[derive(Default)]
+pub struct FullscreenQuad {
+    vbo: Buffer,
+    ibo: Buffer,
+    vao: VertexArray,
+}
+
+impl FullscreenQuad {
+    pub fn new(backend: &mut dyn RenderBackend) -> Result<FullscreenQuad, RenderError> {
+        // position, texture coordinate
+        let vertices: [f32; 20] = [
+            -1., -1., 0., 0., 0.,
+            1., -1., 0., 1., 0.,
+            1., 1., 0., 1., 1.,
+            -1., 1., 0., 0., 1.,
+        ];
+        let indices: [u32; 4] = [0, 1, 2, 3];
+        let layout = VertexLayout::new()
+            .with_attribute("Position", 0, gl::FLOAT, 3, false)
+            .with_attribute("TexCoord", 1, gl::FLOAT, 2, false);
+
+        let quad = FullscreenQuad {
+            vbo: Buffer::new(backend, as_bytes(&vertices[..]), gl::ARRAY_BUFFER, BufferUsage::Static)?,
+            ibo: Buffer::new(backend, as_bytes(&indices[..]), gl::ELEMENT_ARRAY_BUFFER, BufferUsage::Static)?,
+            vao: VertexArray::new(backend)?,
+        };
+
+        backend.bind_vertex_array(quad.vao.id());
+        layout.apply(backend, quad.vbo.id());
+        backend.unbind_vertex_array();
+
+        Ok(quad)
+    }
+
+    pub fn draw(&self, backend: &mut dyn RenderBackend) {
+        backend.bind_vertex_array(self.vao.id());
+        backend.bind_buffer(gl::ELEMENT_ARRAY_BUFFER, self.ibo.id());
+        backend.draw_elements(gl::TRIANGLE_FAN, 4, gl::UNSIGNED_INT, 0);
+        backend.unbind_buffer(gl::ELEMENT_ARRAY_BUFFER);
+        backend.unbind_vertex_array();
+    }
+}
+
+/// Ordered full-screen passes run by `Render` after the scene. While any pass is enabled the
+/// scene is drawn into an offscreen target instead of the window, and each pass reads the
+/// previous one's output; the last enabled pass writes to the window.
+#[derive(Default)]
+pub struct PostProcessStack {
+    pub passes: Vec<PostProcessPass>,
+    targets: Vec<RenderTarget>,
+    quad: Option<FullscreenQuad>,
+}
+
+impl PostProcessStack {
+    /// The built-in passes, all disabled.
+    pub fn builtin() -> Self {
+        PostProcessStack {
+            passes: vec![
+                PostProcessPass::pixelate().with_enabled(false),
+                PostProcessPass::grayscale().with_enabled(false),
+                PostProcessPass::crt().with_enabled(false),
+                PostProcessPass::vignette().with_enabled(false),
+            ],
+            ..Default::default()
+        }
+    }
+
+    pub fn with_pass(mut self, pass: PostProcessPass) -> Self {
+        self.passes.push(pass);
+        self
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.passes.iter().any(|pass| pass.enabled)
+    }
+
+    pub fn pass_mut(&mut self, name: &str) -> Option<&mut PostProcessPass> {
+        self.passes.iter_mut().find(|pass| pass.name == name)
+    }
+
+    /// Compiles missing programs and (re)creates the ping-pong targets at `width`x`height`.
+    /// A pass whose shader fails to build is disabled so the error isn't repeated every frame.
+    pub fn prepare(&mut self, backend: &mut dyn RenderBackend, preprocessor: &Preprocessor, library: &mut ShaderLibrary,
+        width: usize, height: usize) -> Result<(), RenderError> {
+        for pass in self.passes.iter_mut().filter(|pass| pass.enabled && pass.program.is_none()) {
+            let program = library.load(backend, preprocessor, &pass.key())
+                .and_then(|program| set_texture_to_program(backend, gl::TEXTURE0, 0, &program, "Texture").map(|_| program));
+            match program {
+                Ok(program) => pass.program = Some(program),
+                Err(err) => {
+                    pass.enabled = false;
+                    return Err(err);
+                }
+            }
+        }
+
+        if self.quad.is_none() {
+            self.quad = Some(FullscreenQuad::new(backend)?);
+        }
+
+        let resized = self.targets.iter().any(|target| target.width != width || target.height != height);
+        if self.targets.is_empty() || resized {
+            let descriptor = TextureDescriptor::default().with_filter(gl::LINEAR, gl::LINEAR);
+            self.targets = vec![
+                RenderTarget::new(backend, width, height, false, &descriptor)?,
+                RenderTarget::new(backend, width, height, false, &descriptor)?,
+            ];
+        }
+
+        Ok(())
+    }
+
+    /// Target the scene is drawn into, `None` until `prepare` succeeded.
+    pub fn scene_target(&self) -> Option<&RenderTarget> {
+        self.targets.first()
+    }
+
+    /// Runs the enabled passes, reading the scene target. `present` binds whatever the last
+    /// pass should draw into, normally the window.
+    pub fn run(&self, backend: &mut dyn RenderBackend, present: &mut dyn FnMut(&mut dyn RenderBackend)) {
+        let quad = match &self.quad {
+            Some(quad) if self.targets.len() == 2 => quad,
+            _ => return,
+        };
+
+        let passes: Vec<(&PostProcessPass, &Arc<Program>)> = self.passes.iter()
+            .filter(|pass| pass.enabled)
+            .filter_map(|pass| pass.program.as_ref().map(|program| (pass, program)))
+            .collect();
+
+        for (index, (pass, program)) in passes.iter().enumerate() {
+            let source = &self.targets[index % 2];
+            if index + 1 == passes.len() {
+                present(backend);
+            } else {
+                let destination = &self.targets[(index + 1) % 2];
+                destination.bind(backend);
+                backend.clear(gl::COLOR_BUFFER_BIT);
+            }
+
+            let (src, dst) = BlendMode::Opaque.factors();
+            backend.enable_blend(src, dst);
+            backend.use_program(program.id());
+            if program.uniform("Strength").is_some() {
+                if let Err(err) = program.set_uniform(backend, "Strength", &pass.strength) {
+                    eprintln!("{}", err);
+                }
+            }
+            backend.bind_texture_unit(gl::TEXTURE0, source.color.index);
+            quad.draw(backend);
+            backend.unbind_texture();
+        }
+    }
+}
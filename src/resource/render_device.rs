@@ -0,0 +1,10 @@
+use crate::rendering::RenderBackend;
+
+/// The backend every render system issues its calls through.
+pub struct RenderDevice(pub Box<dyn RenderBackend>);
+
+impl RenderDevice {
+    pub fn new<B: RenderBackend + 'static>(backend: B) -> Self {
+        RenderDevice(Box::new(backend))
+    }
+}
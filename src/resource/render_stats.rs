@@ -0,0 +1,9 @@
+/// What `Render` did in the last frame, summed over the window and every offscreen camera.
+#[derive(Default, Debug, Clone, Copy)]
+pub struct RenderStats {
+    /// Entities and instances that passed the visibility test.
+    pub visible: usize,
+    /// Entities and instances skipped because they were outside the camera's view.
+    pub culled: usize,
+    pub draw_calls: usize,
+}
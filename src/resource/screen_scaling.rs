@@ -0,0 +1,67 @@
+use crate::resource::{Projection, Viewport};
+
+/// How the virtual resolution is fitted into the window.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScalingMode {
+    /// The virtual area fills the whole window, distorted if the aspect ratios differ.
+    Stretch,
+    /// The virtual area is scaled uniformly and centered, with bars on the sides that don't fit.
+    #[default]
+    Letterbox,
+    /// Scaled uniformly like `Letterbox`, but the extra space shows more of the world instead of bars.
+    Expand,
+    /// Like `Letterbox`, but only by whole multiples so pixel art stays crisp.
+    Integer,
+}
+
+/// Maps the window's drawable size to the `Viewport` and `Projection` resources. The world is
+/// laid out for `virtual_width` x `virtual_height` units with the origin at the bottom left.
+#[derive(Debug, Clone, Copy)]
+pub struct ScreenScaling {
+    pub mode: ScalingMode,
+    pub virtual_width: f32,
+    pub virtual_height: f32,
+}
+
+impl Default for ScreenScaling {
+    fn default() -> Self {
+        ScreenScaling::new(900., 700.)
+    }
+}
+
+impl ScreenScaling {
+    pub fn new(virtual_width: f32, virtual_height: f32) -> Self {
+        ScreenScaling { mode: ScalingMode::default(), virtual_width, virtual_height }
+    }
+
+    pub fn with_mode(mut self, mode: ScalingMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Viewport and projection for a drawable of `width` x `height` pixels, which on HiDPI
+    /// displays is larger than the window size in points.
+    pub fn apply(&self, width: u32, height: u32) -> (Viewport, Projection) {
+        let (width, height) = (width.max(1) as f32, height.max(1) as f32);
+        let fit = (width / self.virtual_width).min(height / self.virtual_height);
+        let ortho = |right: f32, top: f32| Projection(glm::ortho(0., right, 0., top, -1., 1.));
+        let centered = |scale: f32| {
+            let (scaled_width, scaled_height) = ((self.virtual_width * scale).round(), (self.virtual_height * scale).round());
+            Viewport {
+                x: ((width - scaled_width) / 2.).floor() as i32,
+                y: ((height - scaled_height) / 2.).floor() as i32,
+                width: scaled_width as i32,
+                height: scaled_height as i32,
+            }
+        };
+        let full = Viewport { x: 0, y: 0, width: width as i32, height: height as i32 };
+
+        match self.mode {
+            ScalingMode::Stretch => (full, ortho(self.virtual_width, self.virtual_height)),
+            ScalingMode::Letterbox => (centered(fit), ortho(self.virtual_width, self.virtual_height)),
+            ScalingMode::Expand => (full, ortho(width / fit, height / fit)),
+            // a window smaller than the virtual area is cropped rather than scaled below 1
+            ScalingMode::Integer => (centered(fit.floor().max(1.)), ortho(self.virtual_width, self.virtual_height)),
+        }
+    }
+}
@@ -0,0 +1,171 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use crate::rendering::{RenderBackend, RenderError, Preprocessor, Program, load_program};
+
+/// Identifies one compiled variant of a shader: its name plus the defines it was built with.
+/// Defines are sorted so the same set always maps to the same program.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ShaderKey {
+    pub name: String,
+    pub defines: Vec<String>,
+}
+
+impl ShaderKey {
+    pub fn new(name: &str, defines: &[String]) -> Self {
+        let mut defines = defines.to_vec();
+        defines.sort();
+        defines.dedup();
+
+        ShaderKey { name: name.to_string(), defines }
+    }
+
+    pub fn label(&self) -> String {
+        if self.defines.is_empty() {
+            self.name.clone()
+        } else {
+            format!("{} [{}]", self.name, self.defines.join(", "))
+        }
+    }
+}
+
+/// Compiles every shader variant once and hands out shared handles to it. Variants that
+/// failed to build are remembered so a later reload can retry them.
+#[derive(Default)]
+pub struct ShaderLibrary {
+    programs: HashMap<ShaderKey, Arc<Program>>,
+    failed: HashSet<ShaderKey>,
+}
+
+impl ShaderLibrary {
+    pub fn load(&mut self, backend: &mut dyn RenderBackend, preprocessor: &Preprocessor,
+        key: &ShaderKey) -> Result<Arc<Program>, RenderError> {
+        if let Some(program) = self.programs.get(key) {
+            return Ok(program.clone());
+        }
+
+        self.compile(backend, preprocessor, key)
+    }
+
+    /// Recompiles `key` and replaces the cached program. Handles already given out keep the
+    /// old program until they are swapped for the new one; on error the cache is left untouched.
+    pub fn reload(&mut self, backend: &mut dyn RenderBackend, preprocessor: &Preprocessor,
+        key: &ShaderKey) -> Result<Arc<Program>, RenderError> {
+        self.compile(backend, preprocessor, key)
+    }
+
+    fn compile(&mut self, backend: &mut dyn RenderBackend, preprocessor: &Preprocessor,
+        key: &ShaderKey) -> Result<Arc<Program>, RenderError> {
+        match compile(backend, preprocessor, key) {
+            Ok(program) => {
+                let program = Arc::new(program);
+                self.failed.remove(key);
+                self.programs.insert(key.clone(), program.clone());
+                Ok(program)
+            },
+            Err(err) => {
+                self.failed.insert(key.clone());
+                Err(err)
+            }
+        }
+    }
+
+    pub fn get(&self, key: &ShaderKey) -> Option<Arc<Program>> {
+        self.programs.get(key).cloned()
+    }
+
+    /// Number of handles to `key` held outside the library.
+    pub fn ref_count(&self, key: &ShaderKey) -> usize {
+        self.programs.get(key).map(|program| Arc::strong_count(program) - 1).unwrap_or(0)
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &ShaderKey> {
+        self.programs.keys()
+    }
+
+    /// Variants whose last `load` or `reload` failed and that haven't compiled since.
+    pub fn failed_keys(&self) -> impl Iterator<Item = &ShaderKey> {
+        self.failed.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.programs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.programs.is_empty()
+    }
+
+    /// Drops the programs nobody holds a handle to any more, returning how many were released.
+    /// Failed variants are kept, their users are still waiting for a program.
+    pub fn purge_unused(&mut self) -> usize {
+        let before = self.programs.len();
+        self.programs.retain(|_, program| Arc::strong_count(program) > 1);
+
+        before - self.programs.len()
+    }
+}
+
+fn compile(backend: &mut dyn RenderBackend, preprocessor: &Preprocessor, key: &ShaderKey) -> Result<Program, RenderError> {
+    // the shader stages are released as soon as they go out of scope after linking
+    let preprocessor = preprocessor.clone().with_defines(&key.defines);
+    load_program(backend, &preprocessor, &key.name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rendering::{RecordingBackend, RenderCommand};
+
+    fn preprocessor() -> Preprocessor {
+        Preprocessor {
+            shader_dir: concat!(env!("CARGO_MANIFEST_DIR"), "/shaders").to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn variants_are_shared_and_purged_once_unused() {
+        let mut backend = RecordingBackend::new();
+        let log = backend.log();
+        let preprocessor = preprocessor();
+        let mut library = ShaderLibrary::default();
+        let plain = ShaderKey::new("textured", &[]);
+        let offset = ShaderKey::new("textured", &["UV_OFFSET".to_string()]);
+
+        let first = library.load(&mut backend, &preprocessor, &plain).unwrap();
+        let second = library.load(&mut backend, &preprocessor, &plain).unwrap();
+        let other = library.load(&mut backend, &preprocessor, &offset).unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+        assert_eq!(library.len(), 2);
+        assert_eq!(library.ref_count(&plain), 2);
+        assert_eq!(library.ref_count(&offset), 1);
+
+        let released = other.id();
+        drop(other);
+        drop(second);
+        assert_eq!(library.purge_unused(), 1);
+        assert_eq!(library.keys().collect::<Vec<_>>(), vec![&plain]);
+        assert_eq!(library.ref_count(&plain), 1);
+
+        backend.collect_garbage();
+        assert!(log.commands().contains(&RenderCommand::DeleteProgram { program: released }));
+        assert!(!log.commands().contains(&RenderCommand::DeleteProgram { program: first.id() }));
+    }
+
+    #[test]
+    fn failed_variants_are_kept_until_they_compile() {
+        let mut backend = RecordingBackend::new();
+        let mut library = ShaderLibrary::default();
+        let key = ShaderKey::new("textured", &[]);
+        let broken = Preprocessor { shader_dir: "/nonexistent".to_string(), ..Default::default() };
+
+        assert!(library.load(&mut backend, &broken, &key).is_err());
+        library.purge_unused();
+        assert!(library.is_empty());
+        assert_eq!(library.failed_keys().collect::<Vec<_>>(), vec![&key]);
+
+        let program = library.reload(&mut backend, &preprocessor(), &key).unwrap();
+        assert_eq!(library.failed_keys().count(), 0);
+        assert!(Arc::ptr_eq(&program, &library.get(&key).unwrap()));
+    }
+}
@@ -0,0 +1,66 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime};
+
+/// Polls a shader directory for files whose modification time changed.
+pub struct ShaderWatcher {
+    dir: PathBuf,
+    interval: Duration,
+    last_poll: Instant,
+    modified: HashMap<String, SystemTime>,
+}
+
+impl ShaderWatcher {
+    pub fn new(dir: PathBuf, interval: Duration) -> Self {
+        let mut watcher = ShaderWatcher {
+            dir,
+            interval,
+            last_poll: Instant::now(),
+            modified: HashMap::new(),
+        };
+        watcher.modified = watcher.scan();
+
+        watcher
+    }
+
+    /// File names changed, added or removed since the last poll. Checks the disk at most once per `interval`.
+    pub fn poll(&mut self) -> Vec<String> {
+        if self.last_poll.elapsed() < self.interval {
+            return vec![];
+        }
+        self.last_poll = Instant::now();
+
+        let current = self.scan();
+        let mut changed: Vec<String> = current.iter()
+            .filter(|(name, time)| self.modified.get(*name) != Some(time))
+            .map(|(name, _)| name.clone())
+            .collect();
+        changed.extend(self.modified.keys().filter(|name| !current.contains_key(*name)).cloned());
+        self.modified = current;
+
+        changed
+    }
+
+    fn scan(&self) -> HashMap<String, SystemTime> {
+        let mut files = HashMap::new();
+
+        if let Ok(entries) = std::fs::read_dir(&self.dir) {
+            for entry in entries.flatten() {
+                let modified = entry.metadata().and_then(|metadata| metadata.modified());
+                if let (Some(name), Ok(modified)) = (entry.file_name().to_str(), modified) {
+                    files.insert(name.to_string(), modified);
+                }
+            }
+        }
+
+        files
+    }
+}
+
+/// Latest compile/link error per shader variant, shown in the imgui overlay until it compiles
+/// again, along with the variants the last reload rebuilt.
+#[derive(Default)]
+pub struct ShaderErrors {
+    pub errors: BTreeMap<String, String>,
+    pub reloaded: Vec<String>,
+}
@@ -0,0 +1,138 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use crate::rendering::{RenderBackend, RenderError, Texture, TextureAtlas, TextureDescriptor, load_texture};
+
+/// Decodes and uploads each image file once per descriptor and hands out shared handles to it.
+/// A texture stays alive while any `Sprite`, `Spritesheet` or `Material` holds its handle.
+#[derive(Default)]
+pub struct TextureCache {
+    textures: HashMap<(String, TextureDescriptor), Arc<Texture>>,
+}
+
+impl TextureCache {
+    pub fn load(&mut self, backend: &mut dyn RenderBackend, path: &str,
+        descriptor: &TextureDescriptor) -> Result<Arc<Texture>, RenderError> {
+        let key = cache_key(path, descriptor);
+        if let Some(texture) = self.textures.get(&key) {
+            return Ok(texture.clone());
+        }
+
+        let texture = Arc::new(load_texture(backend, path, descriptor)?);
+        self.textures.insert(key, texture.clone());
+
+        Ok(texture)
+    }
+
+    /// Registers a texture that wasn't loaded from `path`, such as an atlas page, so sprites
+    /// can refer to it by name.
+    pub fn insert(&mut self, path: &str, texture: Arc<Texture>) {
+        let key = cache_key(path, &texture.descriptor);
+        self.textures.insert(key, texture);
+    }
+
+    /// Registers every page of `atlas` under `TextureAtlas::page_name`.
+    pub fn insert_atlas(&mut self, atlas: &TextureAtlas) {
+        for (page, texture) in atlas.pages.iter().enumerate() {
+            self.insert(&atlas.page_name(page), texture.clone());
+        }
+    }
+
+    pub fn get(&self, path: &str, descriptor: &TextureDescriptor) -> Option<Arc<Texture>> {
+        self.textures.get(&cache_key(path, descriptor)).cloned()
+    }
+
+    /// Number of handles to `path` sampled with `descriptor` held outside the cache.
+    pub fn ref_count(&self, path: &str, descriptor: &TextureDescriptor) -> usize {
+        self.textures.get(&cache_key(path, descriptor)).map(|texture| Arc::strong_count(texture) - 1).unwrap_or(0)
+    }
+
+    pub fn len(&self) -> usize {
+        self.textures.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.textures.is_empty()
+    }
+
+    /// Drops the textures nobody holds a handle to any more, returning how many were released.
+    pub fn purge_unused(&mut self) -> usize {
+        let before = self.textures.len();
+        self.textures.retain(|_, texture| Arc::strong_count(texture) > 1);
+
+        before - self.textures.len()
+    }
+}
+
+// `./a.png`, `.\a.png` and `a.png` name the same file
+fn cache_key(path: &str, descriptor: &TextureDescriptor) -> (String, TextureDescriptor) {
+    let path = path.replace('\\', "/");
+    (path.strip_prefix("./").unwrap_or(&path).to_string(), *descriptor)
+}
+
+#[cfg(test)]
+mod tests {
+    use specs::{Builder, RunNow, World, WorldExt};
+    use glm::vec4;
+    use super::*;
+    use crate::component::{Sprite, Material};
+    use crate::rendering::{RecordingBackend, RenderCommand, CommandLog};
+    use crate::resource::RenderDevice;
+    use crate::system::InitSprite;
+
+    fn uploads(log: &CommandLog) -> usize {
+        log.commands().iter().filter(|command| matches!(command, RenderCommand::SetTexture2d { .. })).count()
+    }
+
+    #[test]
+    fn each_file_is_decoded_and_uploaded_once() {
+        let mut backend = RecordingBackend::new();
+        let log = backend.log();
+        let mut cache = TextureCache::default();
+        let descriptor = TextureDescriptor::default();
+
+        let first = cache.load(&mut backend, "./tileset.png", &descriptor).unwrap();
+        let second = cache.load(&mut backend, "tileset.png", &descriptor).unwrap();
+
+        assert!(Arc::ptr_eq(&first, &second));
+        assert_eq!(uploads(&log), 1);
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.ref_count("tileset.png", &descriptor), 2);
+    }
+
+    #[test]
+    fn textures_are_released_after_their_last_holder() {
+        let mut world = World::new();
+        RunNow::setup(&mut InitSprite, &mut world);
+        let backend = RecordingBackend::new();
+        let log = backend.log();
+        world.insert(RenderDevice::new(backend));
+        let descriptor = TextureDescriptor::default();
+        let sprite = |world: &mut World| world.create_entity()
+            .with(Sprite { image_name: "tower.png".to_string(), rect: vec4(0., 0., 205., 198.), ..Default::default() })
+            .build();
+        let (first, second) = (sprite(&mut world), sprite(&mut world));
+
+        InitSprite.run_now(&world);
+
+        // each entity's `Sprite` and `Material` hold a handle
+        let texture = world.read_resource::<TextureCache>().get("tower.png", &descriptor).unwrap().index;
+        assert_eq!(uploads(&log), 1);
+        assert_eq!(world.read_resource::<TextureCache>().ref_count("tower.png", &descriptor), 4);
+
+        world.write_storage::<Material>().remove(first);
+        world.delete_entity(second).unwrap();
+        world.maintain();
+        assert_eq!(world.read_resource::<TextureCache>().ref_count("tower.png", &descriptor), 1);
+        assert_eq!(world.write_resource::<TextureCache>().purge_unused(), 0);
+        world.write_resource::<RenderDevice>().0.collect_garbage();
+        assert!(!log.commands().contains(&RenderCommand::DeleteTexture { texture }));
+
+        world.delete_entity(first).unwrap();
+        world.maintain();
+        assert_eq!(world.write_resource::<TextureCache>().purge_unused(), 1);
+        assert!(world.read_resource::<TextureCache>().is_empty());
+
+        world.write_resource::<RenderDevice>().0.collect_garbage();
+        assert!(log.commands().contains(&RenderCommand::DeleteTexture { texture }));
+    }
+}
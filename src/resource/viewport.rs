@@ -0,0 +1,7 @@
+#[derive(Default, Debug, Clone, Copy)]
+pub struct Viewport {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
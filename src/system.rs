@@ -1,6 +1,9 @@
 pub mod render_system;
 pub mod input_system;
 pub mod sprite_system;
+pub mod shader_system;
+pub mod mesh_system;
+pub mod culling_system;
 
 pub use self::render_system::{
     InitRender, Render};
@@ -8,4 +11,7 @@ pub use self::input_system::KeyboardInput;
 pub use self::sprite_system::{
     InitSprite, 
     InitAnimatedSprite, UpdateAnimatedSprite,
-};
\ No newline at end of file
+};
+pub use self::shader_system::ReloadShaders;
+pub use self::mesh_system::UpdateMeshes;
+pub use self::culling_system::UpdateBounds;
\ No newline at end of file
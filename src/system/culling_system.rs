@@ -0,0 +1,40 @@
+use specs::{ReadStorage, WriteStorage, System, Entities};
+use crate::component::{Mesh, Transform, Bounds};
+use crate::rendering::model_matrix;
+
+/// Keeps every `Mesh` entity's `Bounds` in step with its `Transform`. Has to run before
+/// `UpdateMeshes`, which clears the dirty flags telling it a mesh changed shape.
+pub struct UpdateBounds;
+
+impl<'a> System<'a> for UpdateBounds {
+    type SystemData = (Entities<'a>,
+                    ReadStorage<'a, Transform>,
+                    ReadStorage<'a, Mesh>,
+                    WriteStorage<'a, Bounds>);
+
+    fn run(&mut self, data: Self::SystemData) {
+        use specs::Join;
+
+        let (entities, transforms, meshes, mut bounds) = data;
+
+        for (entity, transform, mesh) in (&entities, &transforms, &meshes).join() {
+            let model = model_matrix(&transform.position, transform.rotation_rad, &transform.scale);
+            match bounds.get_mut(entity) {
+                Some(bounds) => {
+                    if mesh.dirty_vertices.is_some() {
+                        if let Some(local) = mesh.local_bounds() {
+                            bounds.local = local;
+                        }
+                    }
+                    bounds.world = bounds.local.transformed(&model);
+                },
+                None => {
+                    if let Some(local) = mesh.local_bounds() {
+                        let world = local.transformed(&model);
+                        bounds.insert(entity, Bounds { local, world }).unwrap();
+                    }
+                },
+            }
+        }
+    }
+}
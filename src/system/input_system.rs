@@ -1,4 +1,4 @@
-use specs::{Read, ReadStorage, WriteStorage, System, Entities};
+use specs::{Read, WriteStorage, System};
 use crate::component::Transform;
 use crate::resource::{Keyboard, KeycodeEx};
 
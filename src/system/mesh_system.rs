@@ -0,0 +1,114 @@
+use specs::{Write, WriteExpect, WriteStorage, System};
+use crate::component::Mesh;
+use crate::rendering::{RenderBackend, RenderError, Buffer, BufferUsage, as_bytes};
+use crate::resource::{RenderDevice, InstanceGroups};
+
+/// Uploads the vertices and indices of `InstanceGroups` meshes changed since the last frame.
+/// Entity meshes have no buffers of their own, `Render` streams them through the sprite batch
+/// every frame, so their changes are only acknowledged.
+pub struct UpdateMeshes;
+
+impl<'a> System<'a> for UpdateMeshes {
+    type SystemData = (WriteExpect<'a, RenderDevice>,
+                    Write<'a, InstanceGroups>,
+                    WriteStorage<'a, Mesh>);
+
+    fn run(&mut self, data: Self::SystemData) {
+        use specs::Join;
+
+        let (mut device, mut groups, mut meshes) = data;
+        let backend = device.0.as_mut();
+
+        for mesh in (&mut meshes).join().filter(|mesh| mesh.is_dirty()) {
+            mesh.dirty_vertices = None;
+            mesh.dirty_indices = false;
+        }
+
+        for mesh in groups.0.values_mut().map(|group| &mut group.mesh) {
+            if mesh.vao.id() == 0 || !mesh.is_dirty() {
+                continue;
+            }
+            if let Err(err) = upload_mesh(backend, mesh) {
+                eprintln!("{}", err);
+            }
+        }
+    }
+}
+
+fn upload_mesh(backend: &mut dyn RenderBackend, mesh: &mut Mesh) -> Result<(), RenderError> {
+    if let Some(range) = mesh.dirty_vertices.take() {
+        let vertices = &mesh.vertices;
+        // stream buffers are rewritten whole anyway, and a new size needs new storage
+        if mesh.usage == BufferUsage::Stream || vertices.len() != mesh.vbo.size() {
+            mesh.vbo.reallocate(backend, vertices);
+        } else {
+            let range = range.start.min(vertices.len())..range.end.min(vertices.len());
+            mesh.vbo.update(backend, range.start, &vertices[range]);
+        }
+    }
+
+    if mesh.dirty_indices {
+        mesh.dirty_indices = false;
+        let indices = as_bytes(mesh.indices.as_slice());
+        if mesh.ibo.id() == 0 {
+            mesh.ibo = Buffer::new(backend, indices, gl::ELEMENT_ARRAY_BUFFER, mesh.usage)?;
+        } else if mesh.usage == BufferUsage::Stream || indices.len() != mesh.ibo.size() {
+            mesh.ibo.reallocate(backend, indices);
+        } else {
+            mesh.ibo.update(backend, 0, indices);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use specs::{Builder, RunNow, World, WorldExt};
+    use super::*;
+    use crate::component::Material;
+    use crate::rendering::{RecordingBackend, RenderCommand, VertexArray, VertexLayout};
+
+    fn group_mesh(backend: &mut dyn RenderBackend) -> Mesh {
+        let mut mesh = Mesh::new(VertexLayout::sprite()).with_usage(BufferUsage::Dynamic);
+        for _ in 0..4 {
+            mesh.push_vertex(&[&[0., 0., 0.], &[0., 0.], &[1., 1., 1., 1.]]);
+        }
+        mesh.vbo = Buffer::new(backend, &mesh.vertices, gl::ARRAY_BUFFER, mesh.usage).unwrap();
+        mesh.vao = VertexArray::new(backend).unwrap();
+        mesh.dirty_vertices = None;
+        mesh
+    }
+
+    #[test]
+    fn only_the_changed_vertices_of_group_meshes_are_uploaded() {
+        let mut backend = RecordingBackend::new();
+        let log = backend.log();
+        let mut mesh = group_mesh(&mut backend);
+        let vbo = mesh.vbo.id();
+        let stride = mesh.layout.stride();
+        mesh.set_attribute(1, "Color", &[1., 0., 0., 1.]);
+        mesh.set_attribute(2, "Position", &[5., 5., 0.]);
+
+        let mut world = World::new();
+        RunNow::setup(&mut UpdateMeshes, &mut world);
+        world.insert(RenderDevice::new(backend));
+        world.write_resource::<InstanceGroups>().insert("group", mesh, Material::default());
+        let mut entity_mesh = Mesh::new(VertexLayout::sprite());
+        entity_mesh.push_vertex(&[&[1., 2., 3.]]);
+        let entity = world.create_entity().with(entity_mesh).build();
+        log.clear();
+
+        UpdateMeshes.run_now(&world);
+
+        let uploads: Vec<(usize, usize)> = log.commands().into_iter().filter_map(|command| match command {
+            RenderCommand::BufferSubData { index, offset, data, .. } if index == vbo => Some((offset, data.len())),
+            _ => None,
+        }).collect();
+        // vertices 1 and 2 in one upload
+        assert_eq!(uploads, vec![(stride, 2 * stride)]);
+        assert_eq!(log.commands().len(), 1);
+        assert!(!world.read_storage::<Mesh>().get(entity).unwrap().is_dirty());
+        assert!(!world.read_resource::<InstanceGroups>().0["group"].mesh.is_dirty());
+    }
+}
@@ -1,128 +1,570 @@
-use specs::{Read, ReadStorage, WriteStorage, System};
-use glm::vec3;
-use crate::component::{Mesh, Material, Transform};
+use specs::{Read, Write, ReadStorage, WriteStorage, WriteExpect, System};
+use std::collections::HashMap;
+use crate::component::{Mesh, Material, Transform, OffscreenCamera, Instance, RenderLayer, Bounds};
 use crate::rendering::{
-    load_cstring,
-    shader_from_source,
-    create_program,
-    load_texture,
+    RenderBackend,
+    RenderError,
+    Preprocessor,
     set_texture_to_program,
-    new_buffer,
-    new_vertex_array,
-    bind_vertex_array,
-    vertex_attrib_pointer,
-    unbind_vertex_array,
-    bind_texture_unit,
-    unbind_texture,
-    bind_buffer,
-    unbind_buffer,
-    use_program,
-    set_mvp_to_program,
-    push_uniform_vec2
+    as_bytes,
+    Buffer,
+    VertexArray,
+    UniformBlock,
+    FrameData,
+    SpriteBatch,
+    BatchKey,
+    BatchVertex,
+    AttributeReader,
+    DrawOrder,
+    InstanceData,
+    Aabb,
+    model_matrix,
+    FRAME_DATA_BINDING
 };
-use crate::resource::{Camera, Projection};
+use crate::resource::{Camera, Projection, RenderDevice, ElapsedTime, Viewport, ShaderLibrary, ShaderKey, TextureCache, ClearColor, PostProcessStack, InstanceGroups, RenderStats};
 
 pub struct InitRender;
-pub struct Render;
+
+#[derive(Default)]
+pub struct Render {
+    frame_data: Option<UniformBlock>,
+    sprite_batch: SpriteBatch,
+    scratch: IndexScratch,
+}
+
+/// Index lists `draw_scene` reuses from mesh to mesh and frame to frame.
+#[derive(Default)]
+struct IndexScratch {
+    elements: Vec<u32>,
+    list: Vec<u32>,
+}
 
 impl<'a> System<'a> for InitRender {
-    type SystemData = (WriteStorage<'a, Mesh>, 
+    type SystemData = (WriteExpect<'a, RenderDevice>,
+                    Read<'a, Preprocessor>,
+                    Read<'a, Viewport>,
+                    Write<'a, ShaderLibrary>,
+                    Write<'a, TextureCache>,
+                    Write<'a, InstanceGroups>,
+                    ReadStorage<'a, Mesh>,
                     WriteStorage<'a, Material>);
 
     fn run(&mut self, data: Self::SystemData) {
         use specs::Join;
 
-        let (mut mesh, mut material) = data;
+        let (mut device, preprocessor, viewport, mut library, mut textures, mut groups, mesh, mut material) = data;
+        let backend = device.0.as_mut();
 
-        unsafe {
-            gl::Viewport(0,0,900, 700);
-            gl::ClearColor(0.3, 0.3, 0.5, 1.0);
-        }
+        backend.viewport(viewport.x, viewport.y, viewport.width, viewport.height);
 
-        for (mesh, material) in (&mut mesh, &mut material).join() {
-            let vertex_shader_path = format!("shaders\\{}.vs", material.shader);
-            let vertex_shader_src = load_cstring(&vertex_shader_path).map_err(|er| er.to_string()).unwrap();
-            let vertex_shader = shader_from_source(&vertex_shader_src, gl::VERTEX_SHADER).unwrap();
-        
-            let fragment_shader_path = format!("shaders\\{}.fs", material.shader);
-            let fragment_shader_src = load_cstring(&fragment_shader_path).map_err(|er| er.to_string()).unwrap();
-            let fragment_shader = shader_from_source(&fragment_shader_src, gl::FRAGMENT_SHADER).unwrap();
-        
-            material.program = create_program([vertex_shader, fragment_shader]).unwrap();
-
-            if material.texture.width == 0 {
-                let texture_path = format!(".\\{}", material.texture_name);
-                material.texture = load_texture(&texture_path).unwrap();
-                set_texture_to_program(gl::TEXTURE0, material.texture.index, material.program, "Texture");    
+        // entity meshes are streamed through the sprite batch, only instance groups draw from their own buffers
+        for (mesh, material) in (&mesh, &mut material).join() {
+            if let Err(err) = init_material(backend, &preprocessor, &mut library, &mut textures, mesh, material) {
+                eprintln!("{}", err);
+            }
+        }
+        for group in groups.0.values_mut() {
+            let result = init_material(backend, &preprocessor, &mut library, &mut textures, &group.mesh, &mut group.material)
+                .and_then(|_| init_mesh_buffers(backend, &mut group.mesh));
+            if let Err(err) = result {
+                eprintln!("{}", err);
             }
+        }
+
+        backend.collect_garbage();
+    }
+}
 
-            mesh.vertex_vbo = new_buffer::<f32>(mesh.vertices.as_slice(), gl::ARRAY_BUFFER).unwrap();
-            mesh.uv_vbo = new_buffer::<f32>(mesh.uv.as_slice(), gl::ARRAY_BUFFER).unwrap();
-            mesh.colors_vbo = new_buffer::<f32>(mesh.colors.as_slice(), gl::ARRAY_BUFFER).unwrap();
-            mesh.ibo = new_buffer::<u32>(mesh.indices.as_slice(), gl::ELEMENT_ARRAY_BUFFER).unwrap();
+/// Compiles the program and loads the texture of `material`, checking `mesh` fits the program.
+pub(crate) fn init_material(backend: &mut dyn RenderBackend, preprocessor: &Preprocessor, library: &mut ShaderLibrary, textures: &mut TextureCache,
+    mesh: &Mesh, material: &mut Material) -> Result<(), RenderError> {
+    let key = ShaderKey::new(&material.shader, &material.defines);
+    material.program = library.load(backend, preprocessor, &key)?;
 
-            mesh.vao = new_vertex_array().unwrap();
-            bind_vertex_array(mesh.vao);
-            
-            vertex_attrib_pointer(mesh.vertex_vbo, 0, 3, gl::FLOAT, gl::FALSE, (3 * ::std::mem::size_of::<f32>()) as gl::types::GLint);
-            vertex_attrib_pointer(mesh.uv_vbo, 1, 2, gl::FLOAT, gl::FALSE, (2 * ::std::mem::size_of::<f32>()) as gl::types::GLint);
-            vertex_attrib_pointer(mesh.colors_vbo, 2, 4, gl::FLOAT, gl::FALSE, (4 * ::std::mem::size_of::<f32>()) as gl::types::GLint);
+    if material.texture.width == 0 {
+        material.texture = textures.load(backend, &material.texture_name, &material.texture_descriptor)?;
+        set_texture_to_program(backend, gl::TEXTURE0, material.texture.index, &material.program, "Texture")?;
+    }
 
-            unbind_vertex_array();
+    mesh.layout.validate(&material.program)
+}
+
+/// Gives `mesh` its own vertex array and buffers, for meshes that aren't drawn through the sprite batch.
+pub(crate) fn init_mesh_buffers(backend: &mut dyn RenderBackend, mesh: &mut Mesh) -> Result<(), RenderError> {
+    mesh.vbo = Buffer::new(backend, &mesh.vertices, gl::ARRAY_BUFFER, mesh.usage)?;
+    if mesh.is_indexed() {
+        mesh.ibo = Buffer::new(backend, as_bytes(mesh.indices.as_slice()), gl::ELEMENT_ARRAY_BUFFER, mesh.usage)?;
+    }
+    mesh.dirty_vertices = None;
+    mesh.dirty_indices = false;
+
+    mesh.vao = VertexArray::new(backend)?;
+    backend.bind_vertex_array(mesh.vao.id());
+    mesh.layout.apply(backend, mesh.vbo.id());
+    backend.unbind_vertex_array();
+
+    Ok(())
+}
+
+impl Render {
+    fn upload_frame_data(&mut self, backend: &mut dyn RenderBackend, frame_data: &FrameData) {
+        let frame_data = frame_data.to_std140();
+
+        if self.frame_data.is_none() {
+            match UniformBlock::new(backend, FRAME_DATA_BINDING, frame_data.len()) {
+                Ok(block) => self.frame_data = Some(block),
+                Err(err) => eprintln!("{}", err),
+            }
+        }
+        if let Some(block) = &self.frame_data {
+            block.update(backend, &frame_data);
+            block.bind(backend);
         }
     }
 }
 
 impl<'a> System<'a> for Render {
-    type SystemData = (Read<'a, Projection>,
+    type SystemData = (WriteExpect<'a, RenderDevice>,
+                    Read<'a, Projection>,
                     Read<'a, Camera>,
+                    Read<'a, ElapsedTime>,
+                    Read<'a, Viewport>,
+                    Read<'a, ClearColor>,
+                    Read<'a, Preprocessor>,
+                    Write<'a, ShaderLibrary>,
+                    Write<'a, TextureCache>,
+                    Write<'a, PostProcessStack>,
+                    Write<'a, InstanceGroups>,
+                    Write<'a, RenderStats>,
+                    ReadStorage<'a, OffscreenCamera>,
+                    ReadStorage<'a, Instance>,
                     ReadStorage<'a, Transform>,
+                    ReadStorage<'a, RenderLayer>,
+                    ReadStorage<'a, Bounds>,
                     ReadStorage<'a, Mesh>, 
                     ReadStorage<'a, Material>);
 
     fn run(&mut self, data: Self::SystemData) {
         use specs::Join;
 
-        unsafe {
-            gl::Clear(gl::COLOR_BUFFER_BIT);
+        let (mut device, projection, camera, elapsed_time, viewport, clear_color, preprocessor, mut library, mut textures,
+            mut post_process, mut groups, mut stats, offscreen_cameras, instances, transform, layers, bounds, mesh, material) = data;
+        let backend = device.0.as_mut();
+        *stats = RenderStats::default();
+        let scene = Scene { transform: &transform, layers: &layers, bounds: &bounds, mesh: &mesh, material: &material };
 
-            gl::Enable(gl::BLEND);
-            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+        // textures whose last sprite or material went away are queued for deletion here
+        textures.purge_unused();
+        backend.collect_garbage();
+
+        // GL puts the first row at the bottom; flipping y keeps render targets laid out
+        // top row first like every loaded image, so sprite UVs work on them unchanged
+        let flip_y = glm::scaling(&glm::vec3(1., -1., 1.));
+        for offscreen in (&offscreen_cameras).join() {
+            let target = &offscreen.target;
+            target.bind(backend);
+            let color = offscreen.clear_color;
+            backend.clear_color(color.x, color.y, color.z, color.w);
+            backend.clear(target.clear_mask());
+
+            let frame_data = FrameData {
+                projection: flip_y * offscreen.projection,
+                view: glm::translation(&offscreen.position),
+                viewport_size: glm::vec2(target.width as f32, target.height as f32),
+                time: elapsed_time.0,
+            };
+            self.upload_frame_data(backend, &frame_data);
+            let area = Aabb::visible_area(&(frame_data.projection * frame_data.view));
+            // a target can't sample itself while it is being drawn into
+            draw_scene(backend, &mut self.sprite_batch, &mut self.scratch, &scene, &area, Some(target.color.index), &mut stats);
+            draw_instances(backend, &mut groups, &transform, &instances, &area, Some(target.color.index), &mut stats);
         }
 
-        let (projection, camera, transform, mesh, material) = data;
-        let view_matrix = glm::translation(&camera.0);
-        for (transform, mesh, material) in (&transform, &mesh, &material).join() {
-
-            let mut model_matrix = glm::translation(&transform.position);
-            model_matrix = glm::rotate(&model_matrix, transform.rotation_rad, &vec3(0., 0., 1.));
-            model_matrix = glm::scale(&model_matrix, &transform.scale);
-            let mvp = projection.0 * view_matrix * model_matrix;
-            bind_texture_unit(gl::TEXTURE0, material.texture.index);
-
-            use_program(material.program);
-            set_mvp_to_program(&mvp, material.program, "MVPMatrix");
-            push_uniform_vec2(&material.uv_offset, material.program, "Offset");
-
-            bind_vertex_array(mesh.vao);
-
-            bind_buffer(gl::ELEMENT_ARRAY_BUFFER, mesh.ibo);
-            
-            unsafe {    
-                gl::DrawElements(
-                    gl::TRIANGLE_FAN,
-                    4,
-                    gl::UNSIGNED_INT,
-                    std::ptr::null()
-                );
+        // with post-processing on, the scene goes into the stack's first target instead of the window
+        let post_processing = post_process.is_active() && match post_process.prepare(backend, &preprocessor, &mut library,
+            viewport.width as usize, viewport.height as usize) {
+            Ok(()) => post_process.is_active(),
+            Err(err) => {
+                eprintln!("{}", err);
+                false
+            }
+        };
+        match post_process.scene_target().filter(|_| post_processing) {
+            Some(target) => target.bind(backend),
+            None => {
+                backend.bind_framebuffer(0);
+                backend.viewport(viewport.x, viewport.y, viewport.width, viewport.height);
             }
+        }
+        let color = clear_color.0;
+        backend.clear_color(color.x, color.y, color.z, color.w);
+        backend.clear(gl::COLOR_BUFFER_BIT);
+
+        let frame_data = FrameData {
+            projection: projection.0,
+            view: glm::translation(&camera.0),
+            viewport_size: glm::vec2(viewport.width as f32, viewport.height as f32),
+            time: elapsed_time.0,
+        };
+        self.upload_frame_data(backend, &frame_data);
+        let area = Aabb::visible_area(&(frame_data.projection * frame_data.view));
+        draw_scene(backend, &mut self.sprite_batch, &mut self.scratch, &scene, &area, None, &mut stats);
+        draw_instances(backend, &mut groups, &transform, &instances, &area, None, &mut stats);
+
+        if post_processing {
+            // the quad is already in clip space
+            self.upload_frame_data(backend, &FrameData {
+                projection: glm::Mat4::identity(),
+                view: glm::Mat4::identity(),
+                viewport_size: glm::vec2(viewport.width as f32, viewport.height as f32),
+                time: elapsed_time.0,
+            });
+            post_process.run(backend, &mut |backend| {
+                backend.bind_framebuffer(0);
+                backend.viewport(viewport.x, viewport.y, viewport.width, viewport.height);
+            });
+        }
+    }
+}
+
+/// The storages `draw_scene` reads.
+struct Scene<'s, 'a> {
+    transform: &'s ReadStorage<'a, Transform>,
+    layers: &'s ReadStorage<'a, RenderLayer>,
+    bounds: &'s ReadStorage<'a, Bounds>,
+    mesh: &'s ReadStorage<'a, Mesh>,
+    material: &'s ReadStorage<'a, Material>,
+}
+
+/// Queues every mesh whose `Bounds` overlap `area` in world space and draws them through
+/// `batch` back to front by `RenderLayer` and depth, one call per run of equal program,
+/// texture and blend mode. Meshes without `Bounds` yet are always drawn.
+fn draw_scene(backend: &mut dyn RenderBackend, batch: &mut SpriteBatch, scratch: &mut IndexScratch, scene: &Scene, area: &Aabb,
+    skip_texture: Option<gl::types::GLuint>, stats: &mut RenderStats) {
+    use specs::Join;
+
+    for (transform, layer, bounds, mesh, material) in (scene.transform, scene.layers.maybe(), scene.bounds.maybe(),
+        scene.mesh, scene.material).join() {
+        if skip_texture == Some(material.texture.index) {
+            continue;
+        }
+        if bounds.is_some_and(|bounds| !bounds.world.intersects(area)) {
+            stats.culled += 1;
+            continue;
+        }
+        stats.visible += 1;
 
-            use_program(material.program);
+        let model = model_matrix(&transform.position, transform.rotation_rad, &transform.scale);
+        let (position, uv, color) = (mesh.layout.reader("Position"), mesh.layout.reader("TexCoord"), mesh.layout.reader("Color"));
+        let read = |reader: &Option<AttributeReader>, vertex| reader.and_then(|reader| reader.read(&mesh.vertices, vertex));
+        let vertices = (0..mesh.vertex_count()).map(|vertex| {
+            let position = read(&position, vertex).unwrap_or_else(|| glm::vec4(0., 0., 0., 1.));
+            BatchVertex {
+                position: (model * glm::vec4(position.x, position.y, position.z, 1.0)).xyz(),
+                uv: read(&uv, vertex).unwrap_or_else(glm::Vec4::zeros).xy() + material.uv_offset,
+                color: read(&color, vertex).unwrap_or_else(|| glm::vec4(1., 1., 1., 1.)),
+            }
+        });
+
+        let elements = if mesh.is_indexed() {
+            &mesh.indices
+        } else {
+            scratch.elements.clear();
+            scratch.elements.extend(0..mesh.vertex_count() as u32);
+            &scratch.elements
+        };
+        mesh.primitive.append_list(elements, &mut scratch.list);
+
+        let key = BatchKey {
+            program: material.program.clone(),
+            texture: material.texture.index,
+            blend: material.blend,
+            primitive: mesh.primitive.list(),
+        };
+        let layer = layer.copied().unwrap_or_default();
+        let order = DrawOrder {
+            layer: layer.layer,
+            z: transform.position.z,
+            // y points up, so higher sprites are further away
+            y: if layer.y_sort { -transform.position.y } else { 0. },
+        };
+        batch.push(key, order, vertices, scratch.list.drain(..));
+    }
+
+    match batch.flush(backend) {
+        Ok(draw_calls) => stats.draw_calls += draw_calls,
+        Err(err) => eprintln!("{}", err),
+    }
+}
+
+/// Draws every `InstanceGroups` entry with one instanced call covering all its `Instance`s
+/// that overlap `area`. Groups aren't part of the sorted sprite batch: they go on top of it,
+/// ordered by `InstanceGroup::layer` and then name so the result doesn't change between frames.
+fn draw_instances(backend: &mut dyn RenderBackend, groups: &mut InstanceGroups, transform: &ReadStorage<Transform>,
+    instances: &ReadStorage<Instance>, area: &Aabb, skip_texture: Option<gl::types::GLuint>, stats: &mut RenderStats) {
+    use specs::Join;
 
-            unbind_buffer(gl::ELEMENT_ARRAY_BUFFER);
-            
-            unbind_texture();
-            unbind_vertex_array();
+    let local_bounds: HashMap<String, Option<Aabb>> = groups.0.iter()
+        .map(|(name, group)| (name.clone(), group.mesh.local_bounds()))
+        .collect();
+    let mut by_group: HashMap<&str, Vec<InstanceData>> = HashMap::new();
+    for (transform, instance) in (transform, instances).join() {
+        if let Some(Some(local)) = local_bounds.get(instance.group.as_str()) {
+            let model = model_matrix(&transform.position, transform.rotation_rad, &transform.scale);
+            if !local.transformed(&model).intersects(area) {
+                stats.culled += 1;
+                continue;
+            }
         }
+        stats.visible += 1;
+        by_group.entry(instance.group.as_str()).or_default().push(InstanceData {
+            position: transform.position,
+            rotation_rad: transform.rotation_rad,
+            scale: transform.scale,
+            rect: instance.rect,
+            tint: instance.tint,
+        });
     }
-}
\ No newline at end of file
+
+    let mut order: Vec<(i32, String)> = groups.0.iter()
+        .filter(|(name, group)| by_group.contains_key(name.as_str()) && skip_texture != Some(group.material.texture.index))
+        .map(|(name, group)| (group.layer, name.clone()))
+        .collect();
+    order.sort();
+
+    for (_, name) in order {
+        let (group, data) = match (groups.0.get_mut(&name), by_group.get(name.as_str())) {
+            (Some(group), Some(data)) => (group, data),
+            _ => continue,
+        };
+        if let Err(err) = group.instances.upload(backend, group.mesh.vao.id(), data) {
+            eprintln!("{}", err);
+            continue;
+        }
+
+        let (mesh, material) = (&group.mesh, &group.material);
+        let (src, dst) = material.blend.factors();
+        backend.enable_blend(src, dst);
+        backend.use_program(material.program.id());
+        backend.bind_texture_unit(gl::TEXTURE0, material.texture.index);
+        backend.bind_vertex_array(mesh.vao.id());
+
+        let (mode, count) = (mesh.primitive.mode(), mesh.element_count() as i32);
+        if mesh.is_indexed() {
+            backend.bind_buffer(gl::ELEMENT_ARRAY_BUFFER, mesh.ibo.id());
+            backend.draw_elements_instanced(mode, count, gl::UNSIGNED_INT, 0, data.len() as i32);
+            backend.unbind_buffer(gl::ELEMENT_ARRAY_BUFFER);
+        } else {
+            backend.draw_arrays_instanced(mode, 0, count, data.len() as i32);
+        }
+        stats.draw_calls += 1;
+
+        backend.unbind_texture();
+        backend.unbind_vertex_array();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use specs::{Builder, Join, RunNow, World, WorldExt};
+    use glm::{vec2, vec3, vec4, Mat4};
+    use super::*;
+    use crate::component::{Instance, Sprite, Spritesheet, AnimatedSprite};
+    use crate::system::{InitSprite, InitAnimatedSprite};
+    use crate::resource::InstanceGroup;
+    use crate::rendering::{RecordingBackend, RenderCommand, CommandLog, UniformValue, VertexLayout, Primitive};
+
+    fn world(backend: RecordingBackend) -> World {
+        let mut world = World::new();
+        RunNow::setup(&mut InitRender, &mut world);
+        RunNow::setup(&mut Render::default(), &mut world);
+        world.insert(RenderDevice::new(backend));
+        world.insert(Preprocessor {
+            shader_dir: concat!(env!("CARGO_MANIFEST_DIR"), "/shaders").to_string(),
+            ..Default::default()
+        });
+        world.insert(Projection(glm::ortho(0., 900., 0., 700., -1., 1.)));
+        world.insert(Camera(vec3(-10., 20., 0.)));
+        world.insert(Viewport { x: 0, y: 0, width: 900, height: 700 });
+        world
+    }
+
+    fn quad(half_width: f32, half_height: f32) -> Mesh {
+        let mut mesh = Mesh::new(VertexLayout::sprite()).with_primitive(Primitive::TriangleFan);
+        let white: &[f32] = &[1., 1., 1., 1.];
+        mesh.push_vertex(&[&[-half_width, -half_height, 0.], &[0., 1.], white]);
+        mesh.push_vertex(&[&[half_width, -half_height, 0.], &[1., 1.], white]);
+        mesh.push_vertex(&[&[half_width, half_height, 0.], &[1., 0.], white]);
+        mesh.push_vertex(&[&[-half_width, half_height, 0.], &[0., 0.], white]);
+        mesh.indices = vec![0, 1, 2, 3];
+        mesh
+    }
+
+    fn sprite(world: &mut World, position: glm::Vec3, texture_name: &str) {
+        world.create_entity()
+            .with(Transform { position, rotation_rad: 0., scale: vec3(2., 2., 1.) })
+            .with(quad(10., 5.))
+            .with(Material {
+                shader: "textured".to_string(),
+                texture_name: texture_name.to_string(),
+                ..Default::default()
+            })
+            .build();
+    }
+
+    /// Runs `InitRender` and then one frame of `Render`, leaving only the frame's commands in `log`.
+    fn render_frame(world: &World, log: &CommandLog) {
+        InitRender.run_now(world);
+        log.clear();
+        Render::default().run_now(world);
+    }
+
+    fn floats(data: &[u8]) -> Vec<f32> {
+        data.chunks_exact(4).map(|bytes| f32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])).collect()
+    }
+
+    #[test]
+    fn sprites_sharing_a_material_are_drawn_in_one_call() {
+        let backend = RecordingBackend::new();
+        let log = backend.log();
+        let mut world = world(backend);
+        sprite(&mut world, vec3(100., 100., 0.), "tower.png");
+        sprite(&mut world, vec3(300., 300., 0.), "tower.png");
+
+        render_frame(&world, &log);
+
+        assert_eq!(log.draw_count(), 1);
+        let draw = log.commands().into_iter().find(|command| matches!(command, RenderCommand::DrawElements { .. }));
+        // two fans of four vertices, two triangles each
+        assert_eq!(draw, Some(RenderCommand::DrawElements { mode: gl::TRIANGLES, count: 12, index_type: gl::UNSIGNED_INT, offset: 0 }));
+    }
+
+    #[test]
+    fn entity_meshes_are_streamed_without_buffers_of_their_own() {
+        let backend = RecordingBackend::new();
+        let log = backend.log();
+        let mut world = world(backend);
+        sprite(&mut world, vec3(100., 100., 0.), "tower.png");
+
+        InitRender.run_now(&world);
+
+        assert!(!log.commands().iter().any(|command|
+            matches!(command, RenderCommand::NewBuffer { .. } | RenderCommand::NewVertexArray { .. })));
+        assert!((&world.read_storage::<Mesh>()).join().all(|mesh| mesh.vao.id() == 0 && mesh.vbo.id() == 0));
+    }
+
+    #[test]
+    fn instance_groups_are_drawn_after_sprites_by_layer_then_name() {
+        let backend = RecordingBackend::new();
+        let log = backend.log();
+        let mut world = world(backend);
+        sprite(&mut world, vec3(100., 100., 0.), "tower.png");
+        for (name, layer) in [("b", 0), ("a", 1), ("c", 0)] {
+            let material = Material {
+                shader: "instanced".to_string(),
+                texture_name: "tower.png".to_string(),
+                ..Default::default()
+            };
+            world.write_resource::<InstanceGroups>().0.insert(name.to_string(),
+                InstanceGroup::new(quad(10., 5.), material).with_layer(layer));
+            world.create_entity()
+                .with(Transform { position: vec3(200., 200., 0.), rotation_rad: 0., scale: vec3(1., 1., 1.) })
+                .with(Instance { group: name.to_string(), ..Default::default() })
+                .build();
+        }
+
+        render_frame(&world, &log);
+
+        let groups = world.read_resource::<InstanceGroups>();
+        let vaos: Vec<gl::types::GLuint> = ["b", "c", "a"].iter().map(|name| groups.0[*name].mesh.vao.id()).collect();
+        assert!(vaos.iter().all(|&vao| vao != 0));
+        let mut bound = 0;
+        let mut draws = vec![];
+        for command in log.commands() {
+            match command {
+                RenderCommand::BindVertexArray { vao } => bound = vao,
+                RenderCommand::DrawElements { .. } => draws.push(None),
+                RenderCommand::DrawElementsInstanced { .. } => draws.push(Some(bound)),
+                _ => {}
+            }
+        }
+        assert_eq!(draws, vec![None, Some(vaos[0]), Some(vaos[1]), Some(vaos[2])]);
+    }
+
+    #[test]
+    fn animated_and_static_sprites_on_one_texture_share_a_draw() {
+        let backend = RecordingBackend::new();
+        let log = backend.log();
+        let mut world = world(backend);
+        RunNow::setup(&mut InitSprite, &mut world);
+        RunNow::setup(&mut InitAnimatedSprite, &mut world);
+        let rects = vec![vec4(32., 0., 224., 224.), vec4(288., 0., 224., 224.)];
+        world.create_entity()
+            .with(Transform { position: vec3(100., 100., 0.), rotation_rad: 0., scale: vec3(1., 1., 1.) })
+            .with(Sprite { image_name: "tileset.png".to_string(), rect: rects[1], ..Default::default() })
+            .build();
+        world.create_entity()
+            .with(Transform { position: vec3(300., 300., 0.), rotation_rad: 0., scale: vec3(1., 1., 1.) })
+            .with(Spritesheet { image_name: "tileset.png".to_string(), rects: rects.clone(), ..Default::default() })
+            .with(AnimatedSprite { rects: vec![rects.clone()], rect_origin: rects[0], frame_time: 0.5, ..Default::default() })
+            .build();
+        InitSprite.run_now(&world);
+        InitAnimatedSprite.run_now(&world);
+
+        render_frame(&world, &log);
+
+        assert_eq!(log.draw_count(), 1);
+    }
+
+    #[test]
+    fn each_texture_gets_its_own_draw_and_binding() {
+        let backend = RecordingBackend::new();
+        let log = backend.log();
+        let mut world = world(backend);
+        sprite(&mut world, vec3(100., 100., 0.), "tower.png");
+        sprite(&mut world, vec3(300., 300., 0.), "tileset.png");
+
+        render_frame(&world, &log);
+
+        assert_eq!(log.draw_count(), 2);
+        let textures: Vec<gl::types::GLuint> = (&world.read_storage::<Material>()).join()
+            .map(|material| material.texture.index)
+            .collect();
+        for texture in textures {
+            assert!(log.commands().contains(&RenderCommand::BindTextureUnit { active_texture: gl::TEXTURE0, texture }));
+        }
+    }
+
+    #[test]
+    fn vertices_are_moved_to_world_space_and_the_camera_goes_through_frame_data() {
+        let backend = RecordingBackend::new();
+        let log = backend.log();
+        let mut world = world(backend);
+        sprite(&mut world, vec3(100., 200., 0.), "tower.png");
+
+        render_frame(&world, &log);
+        let commands = log.commands();
+
+        // the batch draws in world space, so Model is the identity...
+        let identity = Mat4::identity();
+        assert!(commands.iter().any(|command| matches!(command,
+            RenderCommand::Uniform { name, value: UniformValue::Mat4(value), .. } if name == "Model" && value[0] == identity)));
+
+        // ...and the vertices were transformed on the way in: scale 2, then translate
+        let vertices = commands.iter().find_map(|command| match command {
+            RenderCommand::BufferSubData { target: gl::ARRAY_BUFFER, data, .. } => Some(floats(data)),
+            _ => None,
+        }).unwrap();
+        assert_eq!(&vertices[..3], &[80., 190., 0.]);
+
+        // projection and view reach the shader through the FrameData block
+        let frame_data = FrameData {
+            projection: glm::ortho(0., 900., 0., 700., -1., 1.),
+            view: glm::translation(&vec3(-10., 20., 0.)),
+            viewport_size: vec2(900., 700.),
+            time: 0.,
+        };
+        assert!(commands.iter().any(|command| matches!(command,
+            RenderCommand::BufferSubData { target: gl::UNIFORM_BUFFER, data, .. } if *data == frame_data.to_std140())));
+    }
+}
@@ -0,0 +1,113 @@
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use specs::{Read, ReadStorage, Write, WriteExpect, WriteStorage, System};
+use crate::component::{Mesh, Material};
+use crate::rendering::Preprocessor;
+use crate::resource::{RenderDevice, ShaderErrors, ShaderWatcher, ShaderLibrary, ShaderKey, PostProcessStack, InstanceGroups, TextureCache};
+use crate::system::render_system::{init_material, init_mesh_buffers};
+
+/// Rebuilds the shader variants whose files changed, including the ones that failed before,
+/// and hands the new programs to materials and post-processing passes. Materials still without
+/// a program are initialised again once theirs compiles.
+pub struct ReloadShaders;
+
+impl<'a> System<'a> for ReloadShaders {
+    type SystemData = (WriteExpect<'a, RenderDevice>,
+                    Read<'a, Preprocessor>,
+                    WriteExpect<'a, ShaderWatcher>,
+                    Write<'a, ShaderErrors>,
+                    Write<'a, ShaderLibrary>,
+                    Write<'a, TextureCache>,
+                    Write<'a, PostProcessStack>,
+                    Write<'a, InstanceGroups>,
+                    ReadStorage<'a, Mesh>,
+                    WriteStorage<'a, Material>);
+
+    fn run(&mut self, data: Self::SystemData) {
+        use specs::Join;
+
+        let (mut device, preprocessor, mut watcher, mut errors, mut library, mut textures, mut post_process,
+            mut groups, meshes, mut materials) = data;
+
+        let changed = watcher.poll();
+        if changed.is_empty() {
+            return;
+        }
+
+        // anything that isn't a stage is an include, which any shader may pull in
+        let reload_all = changed.iter().any(|file| !file.ends_with(".vs") && !file.ends_with(".fs"));
+        let changed_shaders: Vec<&str> = changed.iter()
+            .filter_map(|file| file.rsplit_once('.').map(|(stem, _)| stem))
+            .collect();
+
+        let backend = device.0.as_mut();
+
+        // variants no material uses any more aren't worth recompiling
+        library.purge_unused();
+
+        // failed variants aren't in the library, their users are still waiting for a program
+        let failed: BTreeSet<ShaderKey> = library.failed_keys().cloned().collect();
+        let missing = (&materials).join().chain(groups.0.values().map(|group| &group.material))
+            .filter(|material| material.program.id() == 0)
+            .map(|material| ShaderKey::new(&material.shader, &material.defines));
+        let keys: BTreeSet<ShaderKey> = library.keys().cloned()
+            .chain(failed.iter().cloned())
+            .chain(missing)
+            .filter(|key| reload_all || changed_shaders.contains(&key.name.as_str()))
+            .collect();
+
+        errors.reloaded.clear();
+        for key in keys {
+            let label = key.label();
+            match library.reload(backend, &preprocessor, &key) {
+                Ok(_) => {
+                    errors.errors.remove(&label);
+                    errors.reloaded.push(label);
+                },
+                Err(err) => {
+                    eprintln!("{}", err);
+                    errors.errors.insert(label, err.to_string());
+                }
+            }
+        }
+
+        for (mesh, material) in (&meshes, &mut materials).join() {
+            let key = ShaderKey::new(&material.shader, &material.defines);
+            match library.get(&key) {
+                Some(_) if material.program.id() == 0 => {
+                    if let Err(err) = init_material(backend, &preprocessor, &mut library, &mut textures, mesh, material) {
+                        eprintln!("{}", err);
+                    }
+                },
+                Some(program) if !Arc::ptr_eq(&program, &material.program) => material.program = program,
+                _ => {}
+            }
+        }
+        for group in groups.0.values_mut() {
+            let key = ShaderKey::new(&group.material.shader, &group.material.defines);
+            match library.get(&key) {
+                Some(_) if group.material.program.id() == 0 => {
+                    let result = init_material(backend, &preprocessor, &mut library, &mut textures, &group.mesh, &mut group.material)
+                        .and_then(|_| init_mesh_buffers(backend, &mut group.mesh));
+                    if let Err(err) = result {
+                        eprintln!("{}", err);
+                    }
+                },
+                Some(program) if !Arc::ptr_eq(&program, &group.material.program) => group.material.program = program,
+                _ => {}
+            }
+        }
+
+        for pass in post_process.passes.iter_mut() {
+            match (library.get(&pass.key()), &pass.program) {
+                (Some(program), Some(current)) if !Arc::ptr_eq(&program, current) => {
+                    // `PostProcessStack::prepare` picks up the new program and sets its sampler
+                    pass.program = None;
+                },
+                // `prepare` disabled the pass when its shader failed, turn it back on now it builds
+                (Some(_), None) if failed.contains(&pass.key()) => pass.enabled = true,
+                _ => {}
+            }
+        }
+    }
+}
@@ -1,69 +1,71 @@
-use specs::{Read, ReadStorage, WriteStorage, System, Entities};
-use glm::vec2;
+use specs::{Read, Write, ReadStorage, WriteStorage, WriteExpect, System, Entities};
+use glm::{vec2, Vec4};
 use crate::component::{Mesh, Material, Sprite, AnimatedSprite, Spritesheet};
-use crate::rendering::{
-    load_texture,
-};
-use crate::resource::DeltaTime;
+use crate::resource::{DeltaTime, RenderDevice, TextureCache};
+use crate::rendering::{VertexLayout, Primitive};
 
 pub struct InitSprite;
 pub struct InitAnimatedSprite;
 pub struct UpdateAnimatedSprite;
 
+/// A quad the size of `rect` (x, y, width, height in texels), centered on the origin.
+fn quad_mesh(rect: &Vec4, texture_width: f32, texture_height: f32) -> Mesh {
+    let (left, right) = (rect.x / texture_width, (rect.x + rect.z) / texture_width);
+    let (top, bottom) = (rect.y / texture_height, (rect.y + rect.w) / texture_height);
+    let (half_width, half_height) = (rect.z / 2., rect.w / 2.);
+
+    let mut mesh = Mesh::new(VertexLayout::sprite()).with_primitive(Primitive::TriangleFan);
+    mesh.push_vertex(&[&[-half_width, -half_height, 0.0], &[left, bottom], &[1.0, 0.0, 0.0, 1.0]]); // bottom left
+    mesh.push_vertex(&[&[half_width, -half_height, 0.0], &[right, bottom], &[1.0, 1.0, 1.0, 1.0]]); // bottom right
+    mesh.push_vertex(&[&[half_width, half_height, 0.0], &[right, top], &[1.0, 1.0, 1.0, 1.0]]); // top right
+    mesh.push_vertex(&[&[-half_width, half_height, 0.0], &[left, top], &[1.0, 1.0, 1.0, 1.0]]); // top left
+    mesh.indices = vec![0, 1, 2, 3];
+    mesh
+}
+
 impl<'a> System<'a> for InitSprite {
     type SystemData = (Entities<'a>,
-                    ReadStorage<'a, Sprite>,
+                    WriteExpect<'a, RenderDevice>,
+                    Write<'a, TextureCache>,
+                    WriteStorage<'a, Sprite>,
                     WriteStorage<'a, Mesh>,
                     WriteStorage<'a, Material>);
 
     fn run(&mut self, data: Self::SystemData) {
         use specs::Join;
 
-        let (entities, sprites, mut meshes, mut materials) = data;
+        let (entities, mut device, mut textures, mut sprites, mut meshes, mut materials) = data;
 
-        for (entity, sprite) in (&entities, &sprites).join() {
+        for (entity, sprite) in (&entities, &mut sprites).join() {
             println!("entity {:?}", sprite);
-            let texture = load_texture(&sprite.image_name).unwrap();
-
-            let width = sprite.rect.z;
-            let height = sprite.rect.w;
-            meshes.insert(entity, Mesh {
-                vertices: vec![
-                    // positions
-                    -(width as f32)/2., -(height as f32)/2., 0.0, // bottom left
-                    (width as f32)/2., -(height as f32)/2., 0.0, // bottom right
-                    (width as f32)/2., (height as f32)/2., 0.0, // top right
-                    -(width as f32)/2., (height as f32)/2., 0.0, // top left
-                ],
-                uv: vec![
-                    sprite.rect.x / (texture.width as f32), (sprite.rect.y + sprite.rect.w) / (texture.height as f32), // bottom left
-                    (sprite.rect.x + sprite.rect.z) / (texture.width as f32), (sprite.rect.y + sprite.rect.w) / (texture.height as f32), // bottom right
-                    (sprite.rect.x + sprite.rect.z) / (texture.width as f32), sprite.rect.y / (texture.height as f32), // top right
-                    sprite.rect.x / (texture.width as f32), sprite.rect.y / (texture.height as f32), // top left
-                ],
-                colors: vec![
-                    1.0, 0.0, 0.0, 1.0,
-                    1.0, 1.0, 1.0, 1.0,
-                    1.0, 1.0, 1.0, 1.0,
-                    1.0, 1.0, 1.0, 1.0,
-                ],
-                indices: vec![0, 1, 2, 3],
-                ..Default::default()
-            }).unwrap();
+            let texture = match textures.load(device.0.as_mut(), &sprite.image_name, &sprite.texture_descriptor) {
+                Ok(texture) => texture,
+                Err(err) => {
+                    eprintln!("{}", err);
+                    continue;
+                }
+            };
+
+            meshes.insert(entity, quad_mesh(&sprite.rect, texture.width as f32, texture.height as f32)).unwrap();
 
             materials.insert(entity, Material {
                 shader: "textured".to_string(),  
                 texture_name: sprite.image_name.to_string(),
-                texture: texture,
+                texture_descriptor: sprite.texture_descriptor,
+                texture: texture.clone(),
                 ..Default::default()
             }).unwrap();
+
+            sprite.texture = texture;
         }
     }
 }
 
 impl<'a> System<'a> for InitAnimatedSprite {
     type SystemData = (Entities<'a>,
-                    ReadStorage<'a, Spritesheet>,
+                    WriteExpect<'a, RenderDevice>,
+                    Write<'a, TextureCache>,
+                    WriteStorage<'a, Spritesheet>,
                     ReadStorage<'a, AnimatedSprite>,
                     WriteStorage<'a, Mesh>,
                     WriteStorage<'a, Material>);
@@ -71,45 +73,30 @@ impl<'a> System<'a> for InitAnimatedSprite {
     fn run(&mut self, data: Self::SystemData) {
         use specs::Join;
 
-        let (entities, spritesheet, animated_sprite, mut meshes, mut materials) = data;
+        let (entities, mut device, mut textures, mut spritesheet, animated_sprite, mut meshes, mut materials) = data;
 
-        for (entity, spritesheet, animated_sprite) in (&entities, &spritesheet, &animated_sprite).join() {
+        for (entity, spritesheet, animated_sprite) in (&entities, &mut spritesheet, &animated_sprite).join() {
             println!("entity {:?}; {:?}", spritesheet, animated_sprite);
-            let texture = load_texture(&spritesheet.image_name).unwrap();
-
-            let rect = animated_sprite.rects[animated_sprite.current_anim][animated_sprite.current_frame].clone();
-            let width = rect.z;
-            let height = rect.w;
-            meshes.insert(entity, Mesh {
-                vertices: vec![
-                    // positions
-                    -(width as f32)/2., -(height as f32)/2., 0.0, // bottom left
-                    (width as f32)/2., -(height as f32)/2., 0.0, // bottom right
-                    (width as f32)/2., (height as f32)/2., 0.0, // top right
-                    -(width as f32)/2., (height as f32)/2., 0.0, // top left
-                ],
-                uv: vec![
-                    rect.x / (texture.width as f32), (rect.y + rect.w) / (texture.height as f32), // bottom left
-                    (rect.x + rect.z) / (texture.width as f32), (rect.y + rect.w) / (texture.height as f32), // bottom right
-                    (rect.x + rect.z) / (texture.width as f32), rect.y / (texture.height as f32), // top right
-                    rect.x / (texture.width as f32), rect.y / (texture.height as f32), // top left
-                ],
-                colors: vec![
-                    1.0, 0.0, 0.0, 1.0,
-                    1.0, 1.0, 1.0, 1.0,
-                    1.0, 1.0, 1.0, 1.0,
-                    1.0, 1.0, 1.0, 1.0,
-                ],
-                indices: vec![0, 1, 2, 3],
-                ..Default::default()
-            }).unwrap();
+            let texture = match textures.load(device.0.as_mut(), &spritesheet.image_name, &spritesheet.texture_descriptor) {
+                Ok(texture) => texture,
+                Err(err) => {
+                    eprintln!("{}", err);
+                    continue;
+                }
+            };
+
+            let rect = animated_sprite.rects[animated_sprite.current_anim][animated_sprite.current_frame];
+            meshes.insert(entity, quad_mesh(&rect, texture.width as f32, texture.height as f32)).unwrap();
 
             materials.insert(entity, Material {
-                shader: "offset_textured".to_string(),  
+                shader: "textured".to_string(),  
                 texture_name: spritesheet.image_name.to_string(),
-                texture: texture,
+                texture_descriptor: spritesheet.texture_descriptor,
+                texture: texture.clone(),
                 ..Default::default()
             }).unwrap();
+
+            spritesheet.texture = texture;
         }
     }
 }
@@ -140,7 +127,7 @@ impl<'a> System<'a> for UpdateAnimatedSprite {
             println!("sprite.current_frame {}", sprite.current_frame);
             
             let texture = &material.texture;
-            let rect = sprite.rects[sprite.current_anim][sprite.current_frame].clone();
+            let rect = sprite.rects[sprite.current_anim][sprite.current_frame];
             let offset = vec2((rect.x - sprite.rect_origin.x) / texture.width as f32, (rect.y - sprite.rect_origin.y) / texture.height as f32);
 
             println!("Offset {}", offset);